@@ -0,0 +1,187 @@
+use std::str::FromStr;
+
+use crate::colour::Colour;
+use crate::framebuffer::FrameBuffer;
+use crate::rtweekend;
+use crate::tonemap;
+
+/// An arbitrary output variable: a render pass the camera can record beside
+/// the colour, for compositing or for guiding the denoiser.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Aov {
+    /// Surface colour at the first hit, or the background where rays escaped.
+    Albedo,
+    /// World-space surface normal at the first hit, facing the camera; zero
+    /// where rays escaped.
+    Normal,
+    /// Distance from the camera to the first hit; zero where rays escaped.
+    Depth,
+    /// World-space position of the first hit; zero where rays escaped.
+    Position,
+    /// The scene object first hit, numbered from 1 in file order; 0 where
+    /// rays escaped or hit an object that was never numbered.
+    ObjectId,
+    /// The material first hit, numbered from 1 in name order; 0 where rays
+    /// escaped or hit a material that was never numbered.
+    MaterialId,
+    /// Light reaching the camera straight from an emitter or the background,
+    /// or after a single bounce off the first hit.
+    Direct,
+    /// Light reaching the camera after two or more bounces; it adds up with
+    /// [`Aov::Direct`] to the colour.
+    Indirect,
+    /// The variance of each pixel's mean luminance.
+    Variance,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 9] = [
+        Aov::Albedo,
+        Aov::Normal,
+        Aov::Depth,
+        Aov::Position,
+        Aov::ObjectId,
+        Aov::MaterialId,
+        Aov::Direct,
+        Aov::Indirect,
+        Aov::Variance,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Albedo => "albedo",
+            Aov::Normal => "normal",
+            Aov::Depth => "depth",
+            Aov::Position => "position",
+            Aov::ObjectId => "object_id",
+            Aov::MaterialId => "material_id",
+            Aov::Direct => "direct",
+            Aov::Indirect => "indirect",
+            Aov::Variance => "variance",
+        }
+    }
+
+    /// The channels the pass is stored as in an EXR layer named after it;
+    /// single-valued passes keep only the first channel of the layer.
+    pub fn channels(&self) -> &'static [&'static str] {
+        match self {
+            Aov::Albedo | Aov::Direct | Aov::Indirect => &["R", "G", "B"],
+            Aov::Normal | Aov::Position => &["X", "Y", "Z"],
+            Aov::Depth => &["Z"],
+            Aov::ObjectId | Aov::MaterialId => &["id"],
+            Aov::Variance => &["Y"],
+        }
+    }
+
+    /// Whether the pass holds colours that are tone mapped like the render,
+    /// rather than data that has to be visualised to be seen.
+    pub fn is_colour(&self) -> bool {
+        matches!(self, Aov::Albedo | Aov::Direct | Aov::Indirect)
+    }
+
+    /// A picture of the data in `layer`, in linear colours meant for 8-bit
+    /// output without tone mapping. Normals map each axis from `[-1, 1]`,
+    /// depth, variance and position are scaled to the range found in the
+    /// image, and every ID gets its own colour, with black for 0. Colour
+    /// passes come back unchanged.
+    pub fn visualise(&self, layer: &FrameBuffer) -> FrameBuffer {
+        let pixels: Vec<Colour> = layer.pixels().collect();
+        let (min, max) = pixels.iter().fold(
+            (
+                Colour::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+                Colour::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+            ),
+            |(min, max), p| {
+                (
+                    Colour::new(min.x().min(p.x()), min.y().min(p.y()), min.z().min(p.z())),
+                    Colour::new(max.x().max(p.x()), max.y().max(p.y()), max.z().max(p.z())),
+                )
+            },
+        );
+        let scale = |value: f64, min: f64, max: f64| match max > min {
+            true => (value - min) / (max - min),
+            false => 0.0,
+        };
+
+        let linear = |display: Colour| {
+            Colour::new(
+                tonemap::srgb_eotf(display.x()),
+                tonemap::srgb_eotf(display.y()),
+                tonemap::srgb_eotf(display.z()),
+            )
+        };
+
+        let mut image = FrameBuffer::new(layer.width(), layer.height());
+        for (index, p) in pixels.into_iter().enumerate() {
+            let colour = match self {
+                Aov::Albedo | Aov::Direct | Aov::Indirect => p,
+                Aov::Normal => linear(0.5 * p + Colour::new(0.5, 0.5, 0.5)),
+                Aov::Depth | Aov::Variance => {
+                    linear(Colour::new(1.0, 1.0, 1.0) * scale(p.x(), min.x().min(0.0), max.x()))
+                }
+                Aov::Position => linear(Colour::new(
+                    scale(p.x(), min.x(), max.x()),
+                    scale(p.y(), min.y(), max.y()),
+                    scale(p.z(), min.z(), max.z()),
+                )),
+                Aov::ObjectId | Aov::MaterialId => linear(id_colour(p.x().round() as u64)),
+            };
+            image.add_sample(index % layer.width(), index / layer.width(), colour, 1.0);
+        }
+        image
+    }
+}
+
+impl FromStr for Aov {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.to_ascii_lowercase().replace('-', "_");
+        Aov::ALL
+            .into_iter()
+            .find(|aov| aov.name() == name)
+            .ok_or_else(|| {
+                format!(
+                    "unknown AOV '{s}'; expected albedo, normal, depth, position, object-id, \
+                     material-id, direct, indirect or variance"
+                )
+            })
+    }
+}
+
+/// A bright, hashed display colour for `id`, and black for 0.
+fn id_colour(id: u64) -> Colour {
+    if id == 0 {
+        return Colour::default();
+    }
+    let hash = rtweekend::splitmix64(id);
+    let channel = |shift: u32| 0.25 + 0.75 * ((hash >> shift) & 0xff) as f64 / 255.0;
+    Colour::new(channel(0), channel(8), channel(16))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aov_names() {
+        for aov in Aov::ALL {
+            assert!(aov.name().parse::<Aov>() == Ok(aov));
+        }
+        assert!("Object-ID".parse::<Aov>() == Ok(Aov::ObjectId));
+        assert!("beauty".parse::<Aov>().is_err());
+    }
+
+    #[test]
+    fn test_visualise_ids() {
+        let mut layer = FrameBuffer::new(3, 1);
+        for (i, id) in [0.0, 1.0, 2.0].into_iter().enumerate() {
+            layer.add_sample(i, 0, Colour::new(id, id, id), 1.0);
+        }
+        let image = Aov::ObjectId.visualise(&layer);
+
+        assert!(image.pixel(0, 0) == Colour::default());
+        assert!(image.pixel(1, 0) != image.pixel(2, 0));
+        assert!(image.pixel(1, 0).x() > 0.0 && image.pixel(2, 0).x() > 0.0);
+    }
+}
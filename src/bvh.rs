@@ -0,0 +1,196 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::hittable_list::HittableList;
+use crate::interval::Interval;
+use crate::ray::Ray;
+
+const SAH_BUCKETS: usize = 12;
+
+pub struct BvhNode {
+    left: Arc<dyn Hittable>,
+    right: Arc<dyn Hittable>,
+    bbox: Aabb,
+}
+
+impl BvhNode {
+    pub fn new(mut objects: Vec<Arc<dyn Hittable>>) -> Self {
+        assert!(!objects.is_empty(), "cannot build a BVH from no objects");
+        BvhNode::build(&mut objects)
+    }
+
+    pub fn from_list(list: HittableList) -> Self {
+        BvhNode::new(list.into_objects())
+    }
+
+    fn build(objects: &mut [Arc<dyn Hittable>]) -> Self {
+        let (left, right): (Arc<dyn Hittable>, Arc<dyn Hittable>) = match objects.len() {
+            1 => (objects[0].clone(), objects[0].clone()),
+            2 => (objects[0].clone(), objects[1].clone()),
+            _ => {
+                let mid = BvhNode::partition(objects);
+                let (lower, upper) = objects.split_at_mut(mid);
+                (BvhNode::subtree(lower), BvhNode::subtree(upper))
+            }
+        };
+
+        let bbox = Aabb::enclosing(&left.bounding_box(), &right.bounding_box());
+        Self { left, right, bbox }
+    }
+
+    fn subtree(objects: &mut [Arc<dyn Hittable>]) -> Arc<dyn Hittable> {
+        match objects.len() {
+            1 => objects[0].clone(),
+            _ => Arc::new(BvhNode::build(objects)),
+        }
+    }
+
+    // Sorts `objects` along the cheapest axis according to the surface area
+    // heuristic and returns the index at which to split them.
+    fn partition(objects: &mut [Arc<dyn Hittable>]) -> usize {
+        let n = objects.len();
+        let centroid_bounds = objects.iter().fold(Aabb::EMPTY, |acc, object| {
+            let c = object.bounding_box().centroid();
+            Aabb::enclosing(&acc, &Aabb::from_points(c, c))
+        });
+
+        let mut best: Option<(f64, usize, usize)> = None;
+        for axis in 0..3 {
+            let extent = centroid_bounds.axis_interval(axis);
+            if extent.size() <= 0.0 {
+                continue;
+            }
+
+            let mut counts = [0usize; SAH_BUCKETS];
+            let mut bounds = [Aabb::EMPTY; SAH_BUCKETS];
+            for object in objects.iter() {
+                let bbox = object.bounding_box();
+                let b = BvhNode::bucket(bbox.centroid()[axis], extent);
+                counts[b] += 1;
+                bounds[b] = Aabb::enclosing(&bounds[b], &bbox);
+            }
+
+            let mut below = [(0, Aabb::EMPTY); SAH_BUCKETS];
+            let mut above = [(0, Aabb::EMPTY); SAH_BUCKETS];
+            let (mut count, mut bbox) = (0, Aabb::EMPTY);
+            for b in 0..SAH_BUCKETS {
+                count += counts[b];
+                bbox = Aabb::enclosing(&bbox, &bounds[b]);
+                below[b] = (count, bbox);
+            }
+            let (mut count, mut bbox) = (0, Aabb::EMPTY);
+            for b in (0..SAH_BUCKETS).rev() {
+                count += counts[b];
+                bbox = Aabb::enclosing(&bbox, &bounds[b]);
+                above[b] = (count, bbox);
+            }
+
+            for split in 0..SAH_BUCKETS - 1 {
+                let (n_below, box_below) = below[split];
+                let (n_above, box_above) = above[split + 1];
+                if n_below == 0 || n_above == 0 {
+                    continue;
+                }
+
+                let cost = n_below as f64 * box_below.surface_area()
+                    + n_above as f64 * box_above.surface_area();
+                if best.is_none_or(|(best_cost, _, _)| cost < best_cost) {
+                    best = Some((cost, axis, split));
+                }
+            }
+        }
+
+        match best {
+            Some((_, axis, split)) => {
+                let extent = centroid_bounds.axis_interval(axis);
+                objects.sort_by(|a, b| {
+                    a.bounding_box().centroid()[axis].total_cmp(&b.bounding_box().centroid()[axis])
+                });
+                objects
+                    .iter()
+                    .take_while(|object| {
+                        BvhNode::bucket(object.bounding_box().centroid()[axis], extent) <= split
+                    })
+                    .count()
+            }
+            // Every centroid coincides, so no split is better than any other.
+            None => n / 2,
+        }
+    }
+
+    fn bucket(c: f64, extent: Interval) -> usize {
+        let b = (SAH_BUCKETS as f64 * (c - extent.min) / extent.size()) as usize;
+        b.min(SAH_BUCKETS - 1)
+    }
+}
+
+impl Hittable for BvhNode {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        if !self.bbox.hit(r, ray_t) {
+            return false;
+        }
+
+        let hit_left = self.left.hit(r, ray_t, rec);
+        let hit_right = self.right.hit(
+            r,
+            Interval::new(ray_t.min, if hit_left { rec.t } else { ray_t.max }),
+            rec,
+        );
+
+        hit_left || hit_right
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colour::Colour;
+    use crate::material::Lambertian;
+    use crate::rtweekend::{random_float_range, seeded_rng, INFINITY};
+    use crate::sphere::Sphere;
+    use crate::vec3::{Point3, Vec3};
+
+    #[test]
+    fn test_matches_linear_list() {
+        let mat = Arc::new(Lambertian::new(Colour::new(0.5, 0.5, 0.5)));
+        let mut rng = seeded_rng(1);
+        let params: Vec<(Point3, f64)> = (0..200)
+            .map(|_| {
+                (
+                    Vec3::random_range(-10.0, 10.0, &mut rng),
+                    random_float_range(0.1, 1.0, &mut rng),
+                )
+            })
+            .collect();
+        let build = || {
+            let mut list = HittableList::default();
+            params.iter().for_each(|&(center, radius)| {
+                list.add(Arc::new(Sphere::new(center, radius, mat.clone())))
+            });
+            list
+        };
+        let list = build();
+        let bvh = BvhNode::from_list(build());
+
+        for _ in 0..1000 {
+            let r = Ray::new(
+                Vec3::random_range(-15.0, 15.0, &mut rng),
+                Vec3::random_range(-1.0, 1.0, &mut rng),
+            );
+            let mut expected = HitRecord::default();
+            let mut actual = HitRecord::default();
+            let hit_list = list.hit(&r, Interval::new(0.001, INFINITY), &mut expected);
+            let hit_bvh = bvh.hit(&r, Interval::new(0.001, INFINITY), &mut actual);
+
+            assert!(hit_list == hit_bvh);
+            if hit_list {
+                assert!(expected.t == actual.t);
+            }
+        }
+    }
+}
@@ -1,35 +1,87 @@
 use rayon::prelude::*;
 use std::io::{stderr, Write};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
+use crate::aov::Aov;
 use crate::colour;
-use crate::hittable;
+use crate::filter::Filter;
+use crate::framebuffer::{FrameBuffer, RenderLayers};
+use crate::hittable::{self, Hittable};
+use crate::hittable_list::HittableList;
 use crate::interval;
-use crate::material;
+use crate::material::ScatterRecord;
+use crate::pdf::{HittablePdf, Pdf};
 use crate::ray;
 use crate::rtweekend;
+use crate::sampler::{self, Sampler, SamplerKind};
 use crate::vec3::{Point3, Vec3};
 
+/// What a ray sees when it escapes the scene without hitting anything.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Background {
+    /// The white-to-blue vertical gradient from the first book.
+    #[default]
+    Sky,
+    /// A constant colour; black leaves emissive materials as the only light source.
+    Solid(colour::Colour),
+}
+
+impl Background {
+    pub fn colour(&self, r: &ray::Ray) -> colour::Colour {
+        match self {
+            Background::Sky => {
+                let unit_direction = r.direction().unit_vector();
+                let a = 0.5 * (unit_direction.y() + 1.0);
+                (1.0 - a) * colour::Colour::new(1.0, 1.0, 1.0)
+                    + (a * colour::Colour::new(0.5, 0.7, 1.0))
+            }
+            Background::Solid(c) => *c,
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct Camera {
     pub aspect_ratio: f64,
     pub image_width: i64,
+    /// The most samples traced through any pixel.
     pub samples_per_pixel: i32,
+    /// With adaptive sampling, a pixel stops once the standard error of its
+    /// mean luminance falls below this fraction of the mean.
+    pub adaptive_threshold: Option<f64>,
+    /// Samples every pixel takes before adaptive sampling may stop it.
+    pub min_samples: i32,
     pub image_height: i64,
+    /// The most bounces any path can take.
     pub max_depth: i32,
+    /// Bounces before paths become subject to Russian roulette.
+    pub roulette_depth: i32,
+    pub seed: u64,
+    /// Where the pixel, lens, time and scattering dimensions of each camera sample come from.
+    pub sampler: SamplerKind,
+    /// How samples are weighted into the pixels around them.
+    pub filter: Filter,
+    /// The passes [`Camera::render_layers`] records beside the colour.
+    pub aovs: Vec<Aov>,
+    pub tile_size: usize,
+    pub threads: Option<usize>,
+    pub background: Background,
     center: Point3,
     pixel00_loc: Point3,
     pixel_delta_u: Vec3,
     pixel_delta_v: Vec3,
-    vfov: f64,
-    look_from: Point3,
-    look_at: Point3,
-    vup: Vec3,
+    pub vfov: f64,
+    pub look_from: Point3,
+    pub look_at: Point3,
+    pub vup: Vec3,
     u: Vec3,
     v: Vec3,
     w: Vec3,
-    defocus_angle: f64,
-    focus_dist: f64,
+    pub defocus_angle: f64,
+    pub focus_dist: f64,
+    /// Rays are cast at times drawn uniformly from `[shutter_open, shutter_close)`.
+    pub shutter_open: f64,
+    pub shutter_close: f64,
     defocus_disk_u: Vec3,
     defocus_disk_v: Vec3,
 }
@@ -52,13 +104,25 @@ impl Camera {
             aspect_ratio,
             image_width,
             samples_per_pixel,
+            adaptive_threshold: None,
+            min_samples: 16,
             max_depth,
+            roulette_depth: 3,
             vfov,
             look_from,
             look_at,
             vup,
             defocus_angle,
             focus_dist,
+            seed: Default::default(),
+            sampler: SamplerKind::default(),
+            filter: Filter::default(),
+            aovs: Vec::new(),
+            tile_size: 32,
+            threads: None,
+            background: Background::default(),
+            shutter_open: 0.0,
+            shutter_close: 1.0,
             image_height: Default::default(),
             center: Default::default(),
             pixel00_loc: Default::default(),
@@ -72,73 +136,325 @@ impl Camera {
         }
     }
 
-    pub fn render(&mut self, world: &(impl hittable::Hittable + std::marker::Sync)) {
+    /// Renders `world`, sampling the emitters in `lights` directly; an empty
+    /// list renders with scattered rays alone.
+    pub fn render(
+        &mut self,
+        world: &impl hittable::Hittable,
+        lights: &HittableList,
+    ) -> FrameBuffer {
+        self.render_layers(world, lights).colour
+    }
+
+    /// Renders `world` as [`Camera::render`] does, along with each of the
+    /// camera's AOVs.
+    pub fn render_layers(
+        &mut self,
+        world: &impl hittable::Hittable,
+        lights: &HittableList,
+    ) -> RenderLayers {
         self.initialize();
 
-        println!("P3\n{} {}\n255", self.image_width, self.image_height);
-        (0..self.image_height).for_each(|j| {
-            let progress = self.image_height - j;
-            eprint!("\rScanlines remaining: {progress} ");
-            stderr().flush().expect("Unable to flush stderr");
-            (0..self.image_width).for_each(|i| {
-                let pixel_colour = (0..self.samples_per_pixel)
-                    .into_par_iter()
-                    .fold(
-                        || colour::Colour::new(0.0, 0.0, 0.0),
-                        |acc, _| {
-                            acc + Camera::ray_colour(&self.get_ray(i, j), self.max_depth, world)
-                        },
-                    )
-                    .reduce(
-                        || colour::Colour::new(0.0, 0.0, 0.0),
-                        |acc, item| acc + item,
-                    );
-                colour::write_colour(&pixel_colour, self.samples_per_pixel);
-            })
+        let tiles = self.tiles();
+        let tiles_done = AtomicUsize::new(0);
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.threads.unwrap_or(0))
+            .build()
+            .expect("Unable to build render thread pool");
+        let rendered: Vec<_> = pool.install(|| {
+            tiles
+                .par_iter()
+                .map(|tile| {
+                    let rendered = self.render_tile(tile, world, lights);
+
+                    let progress = tiles.len() - tiles_done.fetch_add(1, Ordering::Relaxed) - 1;
+                    eprint!("\rTiles remaining: {progress} ");
+                    stderr().flush().expect("Unable to flush stderr");
+                    rendered
+                })
+                .collect()
         });
         eprintln!("\rDone.                 ");
         stderr().flush().expect("Unable to flush stderr");
+
+        // Filter margins overlap neighbouring tiles, so the tiles are added in
+        // a fixed order to keep the floating-point sums the same on every run.
+        let mut image = RenderLayers::new(
+            self.image_width as usize,
+            self.image_height as usize,
+            &self.aovs,
+        );
+        for (pixels, x0, y0) in &rendered {
+            image.add_tile(pixels, *x0, *y0);
+        }
+        image
     }
 
-    fn ray_colour(r: &ray::Ray, depth: i32, world: &impl hittable::Hittable) -> colour::Colour {
-        if depth <= 0 {
-            return colour::Colour::new(0.0, 0.0, 0.0);
+    fn tiles(&self) -> Vec<Tile> {
+        let tile_size = self.tile_size.max(1) as i64;
+        (0..self.image_height)
+            .step_by(tile_size as usize)
+            .flat_map(|y0| {
+                (0..self.image_width)
+                    .step_by(tile_size as usize)
+                    .map(move |x0| Tile {
+                        x0,
+                        y0,
+                        x1: (x0 + tile_size).min(self.image_width),
+                        y1: (y0 + tile_size).min(self.image_height),
+                    })
+            })
+            .collect()
+    }
+
+    /// Renders the pixels of `tile` into a buffer padded by the filter's
+    /// reach, returned with the image position of its top-left pixel.
+    fn render_tile(
+        &self,
+        tile: &Tile,
+        world: &impl hittable::Hittable,
+        lights: &HittableList,
+    ) -> (RenderLayers, i64, i64) {
+        let margin = self.filter.margin();
+        let (x0, y0) = (tile.x0 - margin, tile.y0 - margin);
+        let mut pixels = RenderLayers::new(
+            (tile.x1 - x0 + margin) as usize,
+            (tile.y1 - y0 + margin) as usize,
+            &self.aovs,
+        );
+        let mut sampler = self
+            .sampler
+            .build(self.seed, self.samples_per_pixel.max(1) as u32);
+
+        for j in tile.y0..tile.y1 {
+            for i in tile.x0..tile.x1 {
+                let (ti, tj) = ((i - x0) as usize, (j - y0) as usize);
+                let mut stats = PixelStats::default();
+                for s in 0..self.samples_per_pixel {
+                    sampler.start_pixel_sample(i, j, s as u32);
+                    let offset = sampler.get_2d().map(|u| u - 0.5);
+                    let r = self.get_ray(i, j, offset, sampler.as_mut());
+                    let (sample, first_hit) = self.ray_colour(&r, world, lights, sampler.as_mut());
+                    stats.add(luminance(sample));
+                    let grey = colour::Colour::new(1.0, 1.0, 1.0);
+                    pixels.add_sample(Aov::Albedo, ti, tj, first_hit.albedo, 1.0);
+                    pixels.add_sample(Aov::Normal, ti, tj, first_hit.normal, 1.0);
+                    pixels.add_sample(Aov::Depth, ti, tj, grey * first_hit.depth, 1.0);
+                    pixels.add_sample(Aov::Position, ti, tj, first_hit.position, 1.0);
+                    // IDs cannot be averaged, so each pixel keeps its first sample's.
+                    if s == 0 {
+                        let object_id = grey * first_hit.object_id as f64;
+                        let material_id = grey * first_hit.material_id as f64;
+                        pixels.add_sample(Aov::ObjectId, ti, tj, object_id, 1.0);
+                        pixels.add_sample(Aov::MaterialId, ti, tj, material_id, 1.0);
+                    }
+
+                    for pj in j - margin..=j + margin {
+                        for pi in i - margin..=i + margin {
+                            let weight = self
+                                .filter
+                                .evaluate(offset[0] - (pi - i) as f64, offset[1] - (pj - j) as f64);
+                            if weight != 0.0 {
+                                let (si, sj) = ((pi - x0) as usize, (pj - y0) as usize);
+                                pixels.colour.add_sample(si, sj, weight * sample, weight);
+                                let direct = weight * first_hit.direct;
+                                let indirect = weight * (sample - first_hit.direct);
+                                pixels.add_sample(Aov::Direct, si, sj, direct, weight);
+                                pixels.add_sample(Aov::Indirect, si, sj, indirect, weight);
+                            }
+                        }
+                    }
+
+                    if let Some(threshold) = self.adaptive_threshold {
+                        if s + 1 >= self.min_samples && stats.converged(threshold) {
+                            break;
+                        }
+                    }
+                }
+                pixels.colour.count_samples(ti, tj, stats.count);
+                let variance = colour::Colour::new(1.0, 1.0, 1.0) * stats.variance_of_mean();
+                pixels.add_sample(Aov::Variance, ti, tj, variance, 1.0);
+            }
         }
+        (pixels, x0, y0)
+    }
 
-        let mut rec = hittable::HitRecord {
-            mat: Arc::new(material::Lambertian {
-                albedo: colour::Colour::default(),
-            }),
-            p: Point3::default(),
-            normal: Vec3::default(),
-            front_face: bool::default(),
-            t: f64::default(),
-        };
-        if world.hit(
-            r,
+    /// Estimates the light arriving along `r` by following a single path.
+    /// Light reaching diffuse surfaces comes both from shadow rays towards
+    /// `lights` and from the scattered ray, with the two combined by multiple
+    /// importance sampling. After `roulette_depth` bounces, paths are ended at
+    /// random with a probability that grows as their throughput falls, and the
+    /// survivors are weighted up to compensate.
+    fn ray_colour(
+        &self,
+        r: &ray::Ray,
+        world: &impl hittable::Hittable,
+        lights: &HittableList,
+        sampler: &mut dyn Sampler,
+    ) -> (colour::Colour, FirstHit) {
+        let mut first_hit = FirstHit::default();
+        let mut colour = colour::Colour::new(0.0, 0.0, 0.0);
+        let mut throughput = colour::Colour::new(1.0, 1.0, 1.0);
+        let mut r = *r;
+        // The density with which the last bounce picked `r`, if light sampling could also have.
+        let mut scatter_pdf: Option<f64> = None;
+
+        for depth in 0..self.max_depth {
+            let mut rec = hittable::HitRecord::default();
+            if !world.hit(
+                &r,
+                interval::Interval::new(0.001, rtweekend::INFINITY),
+                &mut rec,
+            ) {
+                if depth == 0 {
+                    first_hit.albedo = self.background.colour(&r);
+                }
+                let background = throughput * self.background.colour(&r);
+                colour += background;
+                if depth <= 1 {
+                    first_hit.direct += background;
+                }
+                break;
+            }
+            if depth == 0 {
+                first_hit = FirstHit {
+                    albedo: rec.mat.albedo(&rec),
+                    normal: rec.normal,
+                    depth: rec.t * r.direction().length(),
+                    position: rec.p,
+                    object_id: rec.object_id,
+                    material_id: rec.material_id,
+                    direct: colour::Colour::default(),
+                };
+            }
+
+            let mut colour_from_emission = rec.mat.emitted(&r, &rec);
+            // Emitters left out of `lights`, such as moving lights and meshes,
+            // can only be found by scattering, so they keep their full weight.
+            if let Some(scatter_pdf) = scatter_pdf {
+                if !colour_from_emission.near_zero() && hits_sampled_light(lights, &r, rec.t) {
+                    let light_pdf = lights.pdf_value(r.origin(), r.direction());
+                    colour_from_emission =
+                        power_heuristic(scatter_pdf, light_pdf) * colour_from_emission;
+                }
+            }
+            let emission = throughput * colour_from_emission;
+            colour += emission;
+            if depth <= 1 {
+                first_hit.direct += emission;
+            }
+
+            // Every bounce draws the same block of dimensions, whether or not
+            // it uses them all, so that each dimension means the same thing in
+            // every sample of a pixel. Specular materials pick their ray with
+            // `u`; diffuse ones have their direction drawn from it.
+            let u = sampler.get_2d();
+            let [light_x, light_y] = sampler.get_2d();
+            let light_u = [light_x, light_y, sampler.get_1d()];
+            let medium = sampler.get_1d();
+            let roulette = sampler.get_1d();
+            let Some(srec) = rec.mat.scatter(&r, &rec, u) else {
+                break;
+            };
+            match srec {
+                ScatterRecord::Specular { attenuation, ray } => {
+                    throughput *= attenuation;
+                    r = ray.with_sample(medium);
+                    scatter_pdf = None;
+                }
+                ScatterRecord::Diffuse { pdf } => {
+                    if !lights.is_empty() {
+                        let light = throughput
+                            * self.sample_lights(&r, &rec, pdf.as_ref(), world, lights, light_u);
+                        colour += light;
+                        if depth == 0 {
+                            first_hit.direct += light;
+                        }
+                    }
+
+                    let scattered =
+                        ray::Ray::with_time(rec.p, pdf.generate(u), r.time()).with_sample(medium);
+                    let pdf_value = pdf.value(scattered.direction());
+                    if pdf_value <= 0.0 {
+                        break;
+                    }
+                    throughput =
+                        throughput * rec.mat.bsdf(&r, &rec, scattered.direction()) / pdf_value;
+                    r = scattered;
+                    scatter_pdf = Some(pdf_value);
+                }
+            }
+
+            if depth >= self.roulette_depth {
+                let survival = throughput
+                    .x()
+                    .max(throughput.y())
+                    .max(throughput.z())
+                    .min(0.95);
+                if roulette >= survival {
+                    break;
+                }
+                throughput = throughput / survival;
+            }
+        }
+
+        (colour, first_hit)
+    }
+
+    /// Light reaching `rec` along a shadow ray towards a light chosen by the
+    /// first two values of `u`, weighted against the chance of `scatter_pdf`
+    /// picking the same direction. The third picks where the shadow ray
+    /// scatters in volumes.
+    fn sample_lights(
+        &self,
+        r: &ray::Ray,
+        rec: &hittable::HitRecord,
+        scatter_pdf: &dyn Pdf,
+        world: &impl hittable::Hittable,
+        lights: &HittableList,
+        u: [f64; 3],
+    ) -> colour::Colour {
+        let black = colour::Colour::new(0.0, 0.0, 0.0);
+        let light_pdf = HittablePdf::new(lights, rec.p);
+        let shadow_ray = ray::Ray::with_time(rec.p, light_pdf.generate([u[0], u[1]]), r.time())
+            .with_sample(u[2]);
+        let light_pdf_value = light_pdf.value(shadow_ray.direction());
+        let bsdf = rec.mat.bsdf(r, rec, shadow_ray.direction());
+        if light_pdf_value <= 0.0 || bsdf.near_zero() {
+            return black;
+        }
+
+        let mut light_rec = hittable::HitRecord::default();
+        if !world.hit(
+            &shadow_ray,
             interval::Interval::new(0.001, rtweekend::INFINITY),
-            &mut rec,
+            &mut light_rec,
         ) {
-            let mut scattered = ray::Ray::default();
-            let mut attenuation = colour::Colour::default();
-            if rec.mat.scatter(r, &rec, &mut attenuation, &mut scattered) {
-                return attenuation * Camera::ray_colour(&scattered, depth - 1, world);
-            }
-            return colour::Colour::new(0.0, 0.0, 0.0);
+            return black;
         }
 
-        let unit_direction = r.direction().unit_vector();
-        let a = 0.5 * (unit_direction.y() + 1.0);
-        (1.0 - a) * colour::Colour::new(1.0, 1.0, 1.0) + (a * colour::Colour::new(0.5, 0.7, 1.0))
+        // Other emitters in the way are counted when scattering finds them.
+        if !hits_sampled_light(lights, &shadow_ray, light_rec.t) {
+            return black;
+        }
+        let emitted = light_rec.mat.emitted(&shadow_ray, &light_rec);
+        let weight = power_heuristic(light_pdf_value, scatter_pdf.value(shadow_ray.direction()));
+        weight / light_pdf_value * bsdf * emitted
     }
 
-    fn initialize(&mut self) {
-        self.image_height = (self.image_width as f64 / self.aspect_ratio) as i64;
-        self.image_height = if self.image_height < 1 {
+    /// The image height implied by the width and aspect ratio.
+    pub fn output_height(&self) -> i64 {
+        let image_height = (self.image_width as f64 / self.aspect_ratio) as i64;
+        if image_height < 1 {
             1
         } else {
-            self.image_height
-        };
+            image_height
+        }
+    }
+
+    fn initialize(&mut self) {
+        self.image_height = self.output_height();
 
         self.center = self.look_from;
 
@@ -167,30 +483,542 @@ impl Camera {
         self.defocus_disk_v = self.v * defocus_radius;
     }
 
-    fn get_ray(&self, i: i64, j: i64) -> ray::Ray {
+    /// A ray through the point `offset` pixels from the centre of pixel `(i, j)`.
+    fn get_ray(&self, i: i64, j: i64, offset: [f64; 2], sampler: &mut dyn Sampler) -> ray::Ray {
         let pixel_center =
             self.pixel00_loc + (i as f64 * self.pixel_delta_u) + (j as f64 * self.pixel_delta_v);
-        let pixel_sample = pixel_center + self.pixel_sample_square();
+        let pixel_sample =
+            pixel_center + (offset[0] * self.pixel_delta_u) + (offset[1] * self.pixel_delta_v);
 
+        // Every ray draws its lens, time and volume dimensions so that later ones stay aligned.
+        let lens = sampler.get_2d();
         let ray_origin = if self.defocus_angle <= 0.0 {
             self.center
         } else {
-            self.defocus_disk_sample()
+            self.defocus_disk_sample(lens)
         };
         let ray_direction = pixel_sample - ray_origin;
+        let time = sampler.get_1d();
+        let medium = sampler.get_1d();
+        let ray_time = if self.shutter_close > self.shutter_open {
+            self.shutter_open + time * (self.shutter_close - self.shutter_open)
+        } else {
+            self.shutter_open
+        };
+
+        ray::Ray::with_time(ray_origin, ray_direction, ray_time).with_sample(medium)
+    }
+
+    fn defocus_disk_sample(&self, u: [f64; 2]) -> Point3 {
+        let p = sampler::sample_uniform_disk(u);
+        self.center + (self.defocus_disk_u * p[0]) + (self.defocus_disk_v * p[1])
+    }
+}
+
+/// Whether `r` meets one of `lights` at `t`, where it first met the scene,
+/// rather than an emitter that light sampling never picks.
+fn hits_sampled_light(lights: &HittableList, r: &ray::Ray, t: f64) -> bool {
+    let mut rec = hittable::HitRecord::default();
+    lights.hit(
+        r,
+        interval::Interval::new(0.001, t * (1.0 + 1e-9)),
+        &mut rec,
+    ) && rec.t >= t * (1.0 - 1e-9)
+}
+
+/// The multiple importance sampling weight of a sample drawn with density
+/// `pdf` against another strategy with density `other_pdf`.
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    a / (a + b)
+}
+
+/// What a camera sample saw where it first hit the scene.
+#[derive(Default)]
+struct FirstHit {
+    albedo: colour::Colour,
+    normal: Vec3,
+    depth: f64,
+    position: Point3,
+    object_id: u32,
+    material_id: u32,
+    /// The part of the sample's colour that took at most one bounce.
+    direct: colour::Colour,
+}
+
+/// Relative luminance of a linear colour, for judging how noisy a pixel is.
+fn luminance(c: colour::Colour) -> f64 {
+    0.2126 * c.x() + 0.7152 * c.y() + 0.0722 * c.z()
+}
+
+/// A running mean and variance of one pixel's samples, kept with Welford's
+/// algorithm.
+#[derive(Default)]
+struct PixelStats {
+    count: u32,
+    mean: f64,
+    m2: f64,
+}
+
+impl PixelStats {
+    /// Pixels darker than this are judged against it rather than their own
+    /// mean, so near-black noise does not keep them sampling forever.
+    const MIN_MEAN: f64 = 0.01;
 
-        ray::Ray::new(ray_origin, ray_direction)
+    fn add(&mut self, x: f64) {
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (x - self.mean);
     }
 
-    fn pixel_sample_square(&self) -> Vec3 {
-        let px = -0.5 * rtweekend::random_float();
-        let py = -0.5 * rtweekend::random_float();
+    /// The variance of the mean of the samples so far.
+    fn variance_of_mean(&self) -> f64 {
+        match self.count > 1 {
+            true => self.m2 / (self.count - 1) as f64 / self.count as f64,
+            false => 0.0,
+        }
+    }
 
-        (px * self.pixel_delta_u) + (py * self.pixel_delta_v)
+    /// Whether the standard error of the mean is within `threshold` of it.
+    fn converged(&self, threshold: f64) -> bool {
+        if self.count < 2 {
+            return false;
+        }
+        self.variance_of_mean().sqrt() <= threshold * self.mean.max(Self::MIN_MEAN)
     }
+}
 
-    fn defocus_disk_sample(&self) -> Point3 {
-        let p = Vec3::random_in_unit_disk();
-        self.center + (self.defocus_disk_u * p[0]) + (self.defocus_disk_v * p[1])
+struct Tile {
+    x0: i64,
+    y0: i64,
+    x1: i64,
+    y1: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::filter::FilterKind;
+    use crate::material;
+    use crate::quad::Quad;
+    use crate::sphere::Sphere;
+
+    fn render_with_seed(seed: u64) -> Vec<colour::Colour> {
+        let world = HittableList::new(Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, -1.0),
+            0.5,
+            Arc::new(material::Dielectric { ir: 1.5 }),
+        )));
+        let mut cam = Camera::new(
+            2.0,
+            16,
+            8,
+            10,
+            90.0,
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            1.0,
+            1.0,
+        );
+        cam.seed = seed;
+        cam.render(&world, &HittableList::default())
+            .pixels()
+            .collect()
+    }
+
+    #[test]
+    fn test_render_is_reproducible() {
+        assert!(render_with_seed(7) == render_with_seed(7));
+        assert!(render_with_seed(7) != render_with_seed(8));
+    }
+
+    #[test]
+    fn test_wide_filter_render_is_reproducible() {
+        let world = HittableList::new(Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, -1.0),
+            0.5,
+            Arc::new(material::Lambertian::new(colour::Colour::new(
+                0.5, 0.5, 0.5,
+            ))),
+        )));
+        let render = || {
+            let mut cam = Camera::new(
+                1.0,
+                32,
+                4,
+                10,
+                90.0,
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(0.0, 0.0, -1.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                0.0,
+                1.0,
+            );
+            cam.filter = Filter::new(FilterKind::Gaussian);
+            cam.tile_size = 4;
+            cam.threads = Some(8);
+            cam.render(&world, &HittableList::default())
+                .pixels()
+                .collect::<Vec<_>>()
+        };
+        // Tiles share the pixels in their filter margins, whichever thread finishes first.
+        let first = render();
+        for _ in 0..4 {
+            assert!(render() == first);
+        }
+    }
+
+    #[test]
+    fn test_emissive_light_on_black_background() {
+        let light = Arc::new(material::DiffuseLight::new(colour::Colour::new(
+            4.0, 4.0, 4.0,
+        )));
+        let world = HittableList::new(Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, -1.0),
+            0.5,
+            light,
+        )));
+        let mut cam = Camera::new(
+            1.0,
+            5,
+            4,
+            10,
+            90.0,
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            0.0,
+            1.0,
+        );
+        cam.background = Background::Solid(colour::Colour::new(0.0, 0.0, 0.0));
+        let image = cam.render(&world, &world);
+
+        assert!(image.pixel(2, 2) == colour::Colour::new(4.0, 4.0, 4.0));
+        assert!(image.pixel(0, 0) == colour::Colour::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_light_sampling_matches_scattering() {
+        let light = Arc::new(material::DiffuseLight::new(colour::Colour::new(
+            2.0, 2.0, 2.0,
+        )));
+        let white = Arc::new(material::Lambertian::new(colour::Colour::new(
+            0.5, 0.5, 0.5,
+        )));
+        let lamp: Arc<dyn Hittable> = Arc::new(Quad::new(
+            Point3::new(-1.0, 1.0, -2.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 2.0),
+            light,
+        ));
+        let mut world = HittableList::new(Arc::new(Quad::new(
+            Point3::new(-2.0, -1.0, 1.0),
+            Vec3::new(4.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, -4.0),
+            white,
+        )));
+        world.add(lamp.clone());
+        let lights = HittableList::new(lamp);
+
+        let mut cam = Camera::new(
+            1.0,
+            4,
+            1000,
+            4,
+            90.0,
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, -1.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            0.0,
+            1.0,
+        );
+        cam.background = Background::Solid(colour::Colour::new(0.0, 0.0, 0.0));
+        let mean = |image: FrameBuffer| {
+            image.pixels().map(|p| p.x()).sum::<f64>() / (image.width() * image.height()) as f64
+        };
+        let scattered_only = mean(cam.render(&world, &HittableList::default()));
+        let sampled = mean(cam.render(&world, &lights));
+
+        assert!((scattered_only - sampled).abs() < 0.1 * sampled);
+    }
+
+    #[test]
+    fn test_unsampled_emitters_keep_their_weight() {
+        let light = Arc::new(material::DiffuseLight::new(colour::Colour::new(
+            2.0, 2.0, 2.0,
+        )));
+        let white = Arc::new(material::Lambertian::new(colour::Colour::new(
+            0.5, 0.5, 0.5,
+        )));
+        let lamp: Arc<dyn Hittable> = Arc::new(Quad::new(
+            Point3::new(-1.0, 1.0, -2.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 2.0),
+            light.clone(),
+        ));
+        let mut world = HittableList::new(Arc::new(Quad::new(
+            Point3::new(-2.0, -1.0, 1.0),
+            Vec3::new(4.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, -4.0),
+            white,
+        )));
+        world.add(lamp.clone());
+        // An emitter partly hiding the lamp that is never sampled directly.
+        world.add(Arc::new(Quad::new(
+            Point3::new(-1.0, 0.5, -2.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 2.0),
+            light,
+        )));
+        let lights = HittableList::new(lamp);
+
+        let mut cam = Camera::new(
+            1.0,
+            4,
+            1000,
+            4,
+            90.0,
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, -1.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            0.0,
+            1.0,
+        );
+        cam.background = Background::Solid(colour::Colour::new(0.0, 0.0, 0.0));
+        let mean = |image: FrameBuffer| {
+            image.pixels().map(|p| p.x()).sum::<f64>() / (image.width() * image.height()) as f64
+        };
+        let scattered_only = mean(cam.render(&world, &HittableList::default()));
+        let sampled = mean(cam.render(&world, &lights));
+
+        assert!((scattered_only - sampled).abs() < 0.05 * sampled);
+    }
+
+    #[test]
+    fn test_russian_roulette_is_unbiased() {
+        let mut world = HittableList::new(Arc::new(Sphere::new(
+            Point3::new(0.0, -100.5, -1.0),
+            100.0,
+            Arc::new(material::Lambertian::new(colour::Colour::new(
+                0.8, 0.8, 0.8,
+            ))),
+        )));
+        world.add(Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, -1.0),
+            0.5,
+            Arc::new(material::Lambertian::new(colour::Colour::new(
+                0.7, 0.3, 0.3,
+            ))),
+        )));
+        let mut cam = Camera::new(
+            1.0,
+            4,
+            500,
+            50,
+            90.0,
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            0.0,
+            1.0,
+        );
+        let mut mean = |roulette_depth| {
+            cam.roulette_depth = roulette_depth;
+            let image = cam.render(&world, &HittableList::default());
+            image.pixels().map(|p| p.x()).sum::<f64>() / (image.width() * image.height()) as f64
+        };
+        let full_paths = mean(50);
+        let roulette = mean(0);
+
+        assert!((full_paths - roulette).abs() < 0.02 * full_paths);
+    }
+
+    #[test]
+    fn test_samplers_agree() {
+        let world = HittableList::new(Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, -1.0),
+            0.5,
+            Arc::new(material::Lambertian::new(colour::Colour::new(
+                0.5, 0.5, 0.5,
+            ))),
+        )));
+        let mut cam = Camera::new(
+            1.0,
+            4,
+            256,
+            10,
+            90.0,
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            2.0,
+            1.0,
+        );
+        let mut mean = |kind| {
+            cam.sampler = kind;
+            let image = cam.render(&world, &HittableList::default());
+            image.pixels().map(|p| p.x()).sum::<f64>() / (image.width() * image.height()) as f64
+        };
+        let independent = mean(SamplerKind::Independent);
+        for kind in [
+            SamplerKind::Stratified,
+            SamplerKind::Halton,
+            SamplerKind::Sobol,
+            SamplerKind::BlueNoise,
+        ] {
+            assert!((mean(kind) - independent).abs() < 0.02 * independent);
+        }
+    }
+
+    #[test]
+    fn test_filters_preserve_flat_colour() {
+        let world = HittableList::new(Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, 10.0),
+            0.5,
+            Arc::new(material::Lambertian::new(colour::Colour::new(
+                0.5, 0.5, 0.5,
+            ))),
+        )));
+        let mut cam = Camera::new(
+            1.5,
+            9,
+            8,
+            10,
+            90.0,
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            0.0,
+            1.0,
+        );
+        cam.tile_size = 4;
+        let flat = colour::Colour::new(0.2, 0.4, 0.6);
+        cam.background = Background::Solid(flat);
+        for kind in [
+            FilterKind::Box,
+            FilterKind::Tent,
+            FilterKind::Gaussian,
+            FilterKind::Mitchell,
+            FilterKind::Lanczos,
+        ] {
+            cam.filter = Filter::new(kind);
+            let image = cam.render(&world, &HittableList::default());
+            assert!(image.pixels().all(|p| (p - flat).near_zero()));
+            assert!(image.samples(0, 0) == 8 && image.samples(8, 5) == 8);
+        }
+    }
+
+    #[test]
+    fn test_adaptive_sampling_stops_converged_pixels() {
+        let world = HittableList::new(Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, -1.0),
+            0.3,
+            Arc::new(material::Lambertian::new(colour::Colour::new(
+                0.5, 0.5, 0.5,
+            ))),
+        )));
+        let mut cam = Camera::new(
+            1.0,
+            8,
+            256,
+            10,
+            90.0,
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            0.0,
+            1.0,
+        );
+        cam.adaptive_threshold = Some(0.01);
+        let image = cam.render(&world, &HittableList::default());
+
+        assert!(image.samples(0, 0) == cam.min_samples as u32);
+        assert!(image.samples(4, 4) > image.samples(0, 0));
+        assert!(image.samples(4, 4) <= cam.samples_per_pixel as u32);
+    }
+
+    #[test]
+    fn test_direct_and_indirect_add_up() {
+        let mut world = HittableList::new(Arc::new(Sphere::new(
+            Point3::new(0.0, -100.5, -1.0),
+            100.0,
+            Arc::new(material::Lambertian::new(colour::Colour::new(
+                0.8, 0.8, 0.8,
+            ))),
+        )));
+        world.add(Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, -1.0),
+            0.5,
+            Arc::new(material::Lambertian::new(colour::Colour::new(
+                0.7, 0.3, 0.3,
+            ))),
+        )));
+        let mut cam = Camera::new(
+            1.0,
+            6,
+            16,
+            10,
+            90.0,
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            0.0,
+            1.0,
+        );
+        cam.filter = Filter::new(FilterKind::Gaussian);
+        cam.aovs = vec![Aov::Direct, Aov::Indirect, Aov::Depth];
+        let layers = cam.render_layers(&world, &HittableList::default());
+
+        let direct = layers.layer(Aov::Direct).unwrap();
+        let indirect = layers.layer(Aov::Indirect).unwrap();
+        for j in 0..6 {
+            for i in 0..6 {
+                let sum = direct.pixel(i, j) + indirect.pixel(i, j);
+                assert!((sum - layers.colour.pixel(i, j)).near_zero());
+            }
+        }
+        // Sky light reaching the ground by way of the sphere is indirect.
+        assert!(indirect.pixel(3, 5).x() > 0.0);
+        assert!(layers.layer(Aov::Depth).unwrap().pixel(3, 3).x() > 0.49);
+        assert!(layers.layer(Aov::Normal).is_none());
+    }
+
+    #[test]
+    fn test_pixel_stats() {
+        let mut stats = PixelStats::default();
+        for x in [1.0, 2.0, 3.0, 4.0] {
+            stats.add(x);
+        }
+        assert!(stats.mean == 2.5);
+        assert!((stats.m2 / 3.0 - 5.0 / 3.0).abs() < 1e-12);
+        assert!(stats.converged(0.5) && !stats.converged(0.1));
+    }
+
+    #[test]
+    fn test_tiles_cover_image() {
+        let mut cam = Camera::new(
+            16.0 / 9.0,
+            100,
+            1,
+            1,
+            90.0,
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            0.0,
+            1.0,
+        );
+        cam.initialize();
+
+        let covered: i64 = cam
+            .tiles()
+            .iter()
+            .map(|tile| (tile.x1 - tile.x0) * (tile.y1 - tile.y0))
+            .sum();
+        assert!(covered == cam.image_width * cam.image_height);
     }
 }
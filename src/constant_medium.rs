@@ -0,0 +1,153 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::colour::Colour;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::{Isotropic, Material};
+use crate::ray::Ray;
+use crate::sampler;
+use crate::texture::Texture;
+use crate::vec3::Vec3;
+
+/// A volume of constant density filling a convex boundary, such as smoke or
+/// fog. A ray passing through scatters at an exponentially distributed
+/// distance, or passes straight through if that lies beyond the far side.
+pub struct ConstantMedium {
+    boundary: Arc<dyn Hittable>,
+    neg_inv_density: f64,
+    phase_function: Arc<dyn Material>,
+}
+
+impl ConstantMedium {
+    pub fn new(boundary: Arc<dyn Hittable>, density: f64, albedo: Colour) -> Self {
+        ConstantMedium::with_phase_function(boundary, density, Arc::new(Isotropic::new(albedo)))
+    }
+
+    pub fn from_texture(boundary: Arc<dyn Hittable>, density: f64, tex: Arc<dyn Texture>) -> Self {
+        ConstantMedium::with_phase_function(
+            boundary,
+            density,
+            Arc::new(Isotropic::from_texture(tex)),
+        )
+    }
+
+    pub fn with_phase_function(
+        boundary: Arc<dyn Hittable>,
+        density: f64,
+        phase_function: Arc<dyn Material>,
+    ) -> Self {
+        Self {
+            boundary,
+            neg_inv_density: -1.0 / density,
+            phase_function,
+        }
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let mut rec1 = HitRecord::new(self.phase_function.clone());
+        if !self.boundary.hit(r, Interval::UNIVERSE, &mut rec1) {
+            return false;
+        }
+        let t_enter = rec1.t;
+        if !self
+            .boundary
+            .hit(r, Interval::new(t_enter + 0.0001, f64::INFINITY), &mut rec1)
+        {
+            return false;
+        }
+        let t_exit = rec1.t;
+
+        let t1 = t_enter.max(ray_t.min).max(0.0);
+        let t2 = t_exit.min(ray_t.max);
+        if t1 >= t2 {
+            return false;
+        }
+
+        let ray_length = r.direction().length();
+        let distance_inside_boundary = (t2 - t1) * ray_length;
+        // Hashing the ray's sample with where it crosses this boundary lets
+        // media along the same ray scatter independently of each other.
+        let u =
+            sampler::hashed_sample(&[r.sample().to_bits(), t_enter.to_bits(), t_exit.to_bits()]);
+        // 1 - u keeps the logarithm finite, since samples can be 0 but not 1.
+        let hit_distance = self.neg_inv_density * (1.0 - u).ln();
+        if hit_distance > distance_inside_boundary {
+            return false;
+        }
+
+        rec.t = t1 + hit_distance / ray_length;
+        rec.p = r.at(rec.t);
+        // Scattering inside a volume has no surface, so the normal is arbitrary.
+        rec.normal = Vec3::new(1.0, 0.0, 0.0);
+        rec.front_face = true;
+        rec.u = 0.0;
+        rec.v = 0.0;
+        rec.mat = self.phase_function.clone();
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.boundary.bounding_box()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sphere::Sphere;
+    use crate::vec3::Point3;
+
+    #[test]
+    fn test_scatter_distance_follows_density() {
+        let boundary: Arc<dyn Hittable> = Arc::new(Sphere::new(
+            Point3::default(),
+            1.0,
+            Arc::new(Isotropic::new(Colour::default())),
+        ));
+        let thin = ConstantMedium::new(boundary.clone(), 0.01, Colour::default());
+        let dense = ConstantMedium::new(boundary, 100.0, Colour::default());
+        let mut rec = HitRecord::new(thin.phase_function.clone());
+
+        // Transmittance through a chord of length 2 is exp(-2 * density).
+        let ray_t = Interval::new(0.001, f64::INFINITY);
+        let (mut thin_hits, mut dense_hits) = (0, 0);
+        for i in 0..1000 {
+            let r = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0))
+                .with_sample((i as f64 + 0.5) / 1000.0);
+            thin_hits += thin.hit(&r, ray_t, &mut rec) as i32;
+            if dense.hit(&r, ray_t, &mut rec) {
+                dense_hits += 1;
+                assert!(rec.t >= 4.0 && rec.t <= 6.0);
+            }
+        }
+        assert!((15..=25).contains(&thin_hits));
+        assert!(dense_hits == 1000);
+    }
+
+    #[test]
+    fn test_media_along_a_ray_scatter_independently() {
+        // Each sphere lets half the rays through a chord of length 2.
+        let medium = |z: f64| {
+            let boundary = Arc::new(Sphere::new(
+                Point3::new(0.0, 0.0, z),
+                1.0,
+                Arc::new(Isotropic::new(Colour::default())),
+            ));
+            ConstantMedium::new(boundary, 2.0_f64.ln() / 2.0, Colour::default())
+        };
+        let (near, far) = (medium(0.0), medium(-3.0));
+        let mut rec = HitRecord::default();
+
+        let ray_t = Interval::new(0.001, f64::INFINITY);
+        let mut both = 0;
+        for i in 0..1000 {
+            let r = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0))
+                .with_sample((i as f64 + 0.5) / 1000.0);
+            both += (near.hit(&r, ray_t, &mut rec) && far.hit(&r, ray_t, &mut rec)) as i32;
+        }
+        assert!((200..=300).contains(&both));
+    }
+}
@@ -0,0 +1,281 @@
+use rayon::prelude::*;
+
+use crate::aov::Aov;
+use crate::colour::Colour;
+use crate::framebuffer::{FrameBuffer, RenderLayers};
+use crate::vec3::Vec3;
+
+/// An edge-avoiding à-trous wavelet denoiser (Dammertz et al.), which blurs
+/// each pixel with neighbours that share its normal, depth and albedo. It
+/// filters the lighting with the albedo divided out, so textures stay sharp,
+/// and, as in SVGF, scales how different a neighbour's brightness may be by
+/// the pixel's own sampling variance, so well-converged pixels are left be.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Denoiser {
+    /// Passes of the filter; each doubles the spacing of its taps, so the
+    /// footprint grows to `4 * 2^iterations + 1` pixels across.
+    pub iterations: u32,
+    /// Luminance differences, in standard deviations of the pixel's mean,
+    /// beyond which neighbours stop contributing.
+    pub colour_sigma: f64,
+    /// The power the cosine between normals is raised to.
+    pub normal_power: f64,
+    /// Relative depth difference per pixel of distance tolerated between neighbours.
+    pub depth_sigma: f64,
+    /// Albedo difference tolerated between neighbours.
+    pub albedo_sigma: f64,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self {
+            iterations: 5,
+            colour_sigma: 4.0,
+            normal_power: 64.0,
+            depth_sigma: 0.1,
+            albedo_sigma: 0.1,
+        }
+    }
+}
+
+/// Smallest albedo divided out of the colour, so black surfaces do not blow up.
+const MIN_ALBEDO: f64 = 1e-3;
+
+/// The five taps of the B3 spline the à-trous transform is built on.
+const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+struct Pixel {
+    lighting: Colour,
+    variance: f64,
+}
+
+impl Denoiser {
+    /// The AOVs the denoiser is guided by.
+    pub const AOVS: [Aov; 4] = [Aov::Albedo, Aov::Normal, Aov::Depth, Aov::Variance];
+
+    /// The denoised colour layer of `layers`. Any of [`Denoiser::AOVS`]
+    /// missing from `layers` count as the same everywhere, white for the
+    /// albedo and zero otherwise; without the variance nothing is blurred.
+    pub fn denoise(&self, layers: &RenderLayers) -> FrameBuffer {
+        let (width, height) = (layers.colour.width(), layers.colour.height());
+        let layer = |aov, missing: Colour| -> Vec<Colour> {
+            match layers.layer(aov) {
+                Some(layer) => layer.pixels().collect(),
+                None => vec![missing; width * height],
+            }
+        };
+        let albedo = layer(Aov::Albedo, Colour::new(1.0, 1.0, 1.0));
+        let normal = layer(Aov::Normal, Vec3::default());
+        let depth: Vec<f64> = layer(Aov::Depth, Colour::default())
+            .iter()
+            .map(|d| d.x())
+            .collect();
+        let variance = layer(Aov::Variance, Colour::default());
+
+        let mut pixels: Vec<Pixel> = layers
+            .colour
+            .pixels()
+            .zip(variance)
+            .zip(&albedo)
+            .map(|((colour, variance), albedo)| {
+                let albedo = demodulator(*albedo);
+                let albedo_luminance = luminance(albedo);
+                let mut lighting = colour;
+                lighting /= albedo;
+                Pixel {
+                    lighting,
+                    variance: variance.x() / (albedo_luminance * albedo_luminance),
+                }
+            })
+            .collect();
+        let rows: Vec<usize> = (0..height).collect();
+
+        for iteration in 0..self.iterations {
+            let step = 1 << iteration;
+            let filtered: Vec<Vec<Pixel>> = rows
+                .par_iter()
+                .map(|&j| {
+                    (0..width)
+                        .map(|i| {
+                            self.filter_pixel(
+                                &pixels, &albedo, &normal, &depth, width, height, i, j, step,
+                            )
+                        })
+                        .collect()
+                })
+                .collect();
+            pixels = filtered.into_iter().flatten().collect();
+        }
+
+        let mut image = FrameBuffer::new(width, height);
+        for j in 0..height {
+            for i in 0..width {
+                let p = j * width + i;
+                let colour = pixels[p].lighting * demodulator(albedo[p]);
+                image.add_sample(i, j, colour, 1.0);
+                image.count_samples(i, j, layers.colour.samples(i, j));
+            }
+        }
+        image
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn filter_pixel(
+        &self,
+        pixels: &[Pixel],
+        albedo: &[Colour],
+        normal: &[Vec3],
+        depth: &[f64],
+        width: usize,
+        height: usize,
+        i: usize,
+        j: usize,
+        step: usize,
+    ) -> Pixel {
+        let p = j * width + i;
+        let centre = &pixels[p];
+        let centre_luminance = luminance(centre.lighting);
+        let luminance_scale =
+            self.colour_sigma * blurred_variance(pixels, width, height, i, j).sqrt() + 1e-10;
+
+        let mut sum = Colour::default();
+        let mut variance = 0.0;
+        let mut total_weight = 0.0;
+        for (ky, wy) in KERNEL.iter().enumerate() {
+            let qj = j as i64 + (ky as i64 - 2) * step as i64;
+            if qj < 0 || qj >= height as i64 {
+                continue;
+            }
+            for (kx, wx) in KERNEL.iter().enumerate() {
+                let qi = i as i64 + (kx as i64 - 2) * step as i64;
+                if qi < 0 || qi >= width as i64 {
+                    continue;
+                }
+                let q = qj as usize * width + qi as usize;
+                let neighbour = &pixels[q];
+
+                let distance = (((qi - i as i64).pow(2) + (qj - j as i64).pow(2)) as f64).sqrt();
+                let luminance_weight =
+                    -(luminance(neighbour.lighting) - centre_luminance).abs() / luminance_scale;
+                let albedo_weight = -(albedo[p] - albedo[q]).length_squared()
+                    / (self.albedo_sigma * self.albedo_sigma);
+                let depth_weight = -(depth[p] - depth[q]).abs()
+                    / (self.depth_sigma * depth[p].max(1e-3) * distance.max(1.0));
+                let weight = wx
+                    * wy
+                    * normal_weight(normal[p], normal[q], self.normal_power)
+                    * (luminance_weight + albedo_weight + depth_weight).exp();
+
+                sum += weight * neighbour.lighting;
+                variance += weight * weight * neighbour.variance;
+                total_weight += weight;
+            }
+        }
+
+        Pixel {
+            lighting: sum / total_weight,
+            variance: variance / (total_weight * total_weight),
+        }
+    }
+}
+
+/// The variance around pixel `(i, j)`, smoothed over its neighbours since a
+/// single pixel's estimate is itself noisy.
+fn blurred_variance(pixels: &[Pixel], width: usize, height: usize, i: usize, j: usize) -> f64 {
+    const GAUSSIAN: [f64; 3] = [0.25, 0.5, 0.25];
+    let mut sum = 0.0;
+    let mut total_weight = 0.0;
+    for (ky, wy) in GAUSSIAN.iter().enumerate() {
+        for (kx, wx) in GAUSSIAN.iter().enumerate() {
+            let (qi, qj) = (i as i64 + kx as i64 - 1, j as i64 + ky as i64 - 1);
+            if qi < 0 || qj < 0 || qi >= width as i64 || qj >= height as i64 {
+                continue;
+            }
+            sum += wx * wy * pixels[qj as usize * width + qi as usize].variance;
+            total_weight += wx * wy;
+        }
+    }
+    sum / total_weight
+}
+
+/// The albedo divided out of a pixel's colour before filtering.
+fn demodulator(albedo: Colour) -> Colour {
+    Colour::new(
+        albedo.x().max(MIN_ALBEDO),
+        albedo.y().max(MIN_ALBEDO),
+        albedo.z().max(MIN_ALBEDO),
+    )
+}
+
+/// How alike two normals are; pixels where rays escaped, which have no
+/// normal, are only alike each other.
+fn normal_weight(a: Vec3, b: Vec3, power: f64) -> f64 {
+    match (a.near_zero(), b.near_zero()) {
+        (true, true) => 1.0,
+        (false, false) => {
+            let cosine = a.dot(b) / (a.length() * b.length());
+            cosine.max(0.0).powf(power)
+        }
+        _ => 0.0,
+    }
+}
+
+fn luminance(c: Colour) -> f64 {
+    0.2126 * c.x() + 0.7152 * c.y() + 0.0722 * c.z()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtweekend;
+
+    // A 32x32 image of two flat walls meeting down the middle, lit to 0.2 and
+    // 0.8 and covered in noise of the given standard deviation.
+    fn noisy_walls(noise: f64) -> RenderLayers {
+        let mut rng = rtweekend::seeded_rng(11);
+        let mut layers = RenderLayers::new(32, 32, &Denoiser::AOVS);
+        for j in 0..32 {
+            for i in 0..32 {
+                let (level, n) = match i < 16 {
+                    true => (0.2, Vec3::new(1.0, 0.0, 0.0)),
+                    false => (0.8, Vec3::new(0.0, 0.0, 1.0)),
+                };
+                let jitter = noise * (rtweekend::random_float(&mut rng) - 0.5) * 12.0_f64.sqrt();
+                let grey = Colour::new(1.0, 1.0, 1.0);
+                layers.colour.add_sample(i, j, (level + jitter) * grey, 1.0);
+                layers.add_sample(Aov::Albedo, i, j, grey, 1.0);
+                layers.add_sample(Aov::Normal, i, j, n, 1.0);
+                layers.add_sample(Aov::Depth, i, j, 5.0 * grey, 1.0);
+                layers.add_sample(Aov::Variance, i, j, noise * noise * grey, 1.0);
+            }
+        }
+        layers
+    }
+
+    fn error(image: &FrameBuffer) -> f64 {
+        let squared: f64 = (0..32)
+            .flat_map(|j| (0..32).map(move |i| (i, j)))
+            .map(|(i, j)| {
+                let expected = if i < 16 { 0.2 } else { 0.8 };
+                (image.pixel(i, j).y() - expected).powi(2)
+            })
+            .sum();
+        (squared / 1024.0).sqrt()
+    }
+
+    #[test]
+    fn test_denoise_removes_noise_but_keeps_edges() {
+        let layers = noisy_walls(0.05);
+        let denoised = Denoiser::default().denoise(&layers);
+        assert!(error(&denoised) < 0.25 * error(&layers.colour));
+        assert!((denoised.pixel(15, 10).y() - 0.2).abs() < 0.05);
+        assert!((denoised.pixel(16, 10).y() - 0.8).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_denoise_leaves_converged_pixels() {
+        let layers = noisy_walls(0.0);
+        let denoised = Denoiser::default().denoise(&layers);
+        assert!(error(&denoised) < 1e-9);
+    }
+}
@@ -0,0 +1,172 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::{self, HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::rtweekend::PI;
+use crate::sampler;
+use crate::vec3::{Onb, Point3, Vec3};
+
+pub struct Disk {
+    center: Point3,
+    normal: Vec3,
+    radius: f64,
+    tangent: Vec3,
+    bitangent: Vec3,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+
+impl Disk {
+    pub fn new(center: Point3, normal: Vec3, radius: f64, mat: Arc<dyn Material>) -> Self {
+        let normal = normal.unit_vector();
+
+        // The disk's extent along each axis shrinks as the normal tilts towards it.
+        let extent = Vec3::new(
+            radius * (1.0 - normal.x() * normal.x()).max(0.0).sqrt(),
+            radius * (1.0 - normal.y() * normal.y()).max(0.0).sqrt(),
+            radius * (1.0 - normal.z() * normal.z()).max(0.0).sqrt(),
+        );
+
+        // Texture coordinates keep the frame disks had before using Onb: its
+        // v axis is the tangent and -u the bitangent.
+        let uvw = Onb::new(normal);
+
+        Self {
+            center,
+            normal,
+            radius,
+            tangent: uvw.v(),
+            bitangent: -uvw.u(),
+            mat,
+            bbox: Aabb::from_points(center - extent, center + extent),
+        }
+    }
+}
+
+impl Hittable for Disk {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let denom = self.normal.dot(r.direction());
+        if denom.abs() < 1e-8 {
+            return false;
+        }
+
+        let t = self.normal.dot(self.center - r.origin()) / denom;
+        if !ray_t.contains(t) {
+            return false;
+        }
+
+        let intersection = r.at(t);
+        let offset = intersection - self.center;
+        if offset.length_squared() > self.radius * self.radius {
+            return false;
+        }
+
+        // Polar texture coordinates: u is the angle around the normal, v the distance from the center.
+        let phi = offset.dot(self.bitangent).atan2(offset.dot(self.tangent)) + PI;
+
+        rec.t = t;
+        rec.p = intersection;
+        rec.u = phi / (2.0 * PI);
+        rec.v = offset.length() / self.radius;
+        rec.mat = self.mat.clone();
+        rec.set_face_normal(r, &self.normal);
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let area = PI * self.radius * self.radius;
+        hittable::area_pdf_value(self, area, origin, direction)
+    }
+
+    fn random(&self, origin: Point3, u: [f64; 2]) -> Vec3 {
+        let d = self.radius * sampler::sample_uniform_disk(u);
+        let p = self.center + (d.x() * self.tangent) + (d.y() * self.bitangent);
+        p - origin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colour::Colour;
+    use crate::material::Lambertian;
+
+    fn disk() -> Disk {
+        Disk::new(
+            Point3::new(0.0, 0.0, -2.0),
+            Vec3::new(0.0, 0.0, 2.0),
+            1.0,
+            Arc::new(Lambertian::new(Colour::default())),
+        )
+    }
+
+    #[test]
+    fn test_hit() {
+        let disk = disk();
+        let inside = Ray::new(Point3::new(0.5, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let outside = Ray::new(Point3::new(0.8, 0.8, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let behind = Ray::new(Point3::new(0.0, 0.5, -4.0), Vec3::new(0.0, 0.0, 1.0));
+
+        let mut rec = HitRecord::default();
+        assert!(disk.hit(&inside, Interval::new(0.001, f64::INFINITY), &mut rec));
+        assert!(rec.t == 2.0);
+        assert!(rec.front_face);
+        assert!(rec.normal == Vec3::new(0.0, 0.0, 1.0));
+        // v is the distance from the center as a fraction of the radius.
+        assert!(rec.v == 0.5);
+        assert!((0.0..=1.0).contains(&rec.u));
+
+        assert!(disk.hit(&behind, Interval::new(0.001, f64::INFINITY), &mut rec));
+        assert!(!rec.front_face);
+        assert!(rec.normal == Vec3::new(0.0, 0.0, -1.0));
+
+        assert!(!disk.hit(&outside, Interval::new(0.001, f64::INFINITY), &mut rec));
+    }
+
+    #[test]
+    fn test_uv_orientation() {
+        // Facing +z, the tangent is +y and the bitangent -x.
+        let disk = disk();
+        let mut rec = HitRecord::default();
+        for (x, y, u) in [(0.0, 0.5, 0.5), (-0.5, 0.0, 0.75), (0.5, 0.0, 0.25)] {
+            let r = Ray::new(Point3::new(x, y, 0.0), Vec3::new(0.0, 0.0, -1.0));
+            assert!(disk.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+            assert!((rec.u - u).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let bbox = disk().bounding_box();
+        assert!(bbox.x.min == -1.0 && bbox.x.max == 1.0);
+        assert!(bbox.y.min == -1.0 && bbox.y.max == 1.0);
+        assert!(bbox.z.size() < 1e-3);
+    }
+
+    #[test]
+    fn test_area_sampling() {
+        let disk = disk();
+        let origin = Point3::new(0.5, 0.5, 0.0);
+        for u in [[0.1, 0.2], [0.9, 0.7], [0.5, 0.5], [0.0, 1.0]] {
+            let direction = disk.random(origin, u);
+            let mut rec = HitRecord::default();
+            let r = Ray::new(origin, direction);
+            assert!(disk.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+            assert!((rec.t - 1.0).abs() < 1e-9);
+
+            let cosine = direction.z().abs() / direction.length();
+            let expected = direction.length_squared() / (cosine * PI);
+            assert!((disk.pdf_value(origin, direction) - expected).abs() < 1e-9);
+        }
+        let away = Vec3::new(0.0, 0.0, 1.0);
+        assert!(disk.pdf_value(origin, away) == 0.0);
+    }
+}
@@ -0,0 +1,182 @@
+use std::io::{self, Write};
+
+const MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+const VERSION: i32 = 2;
+const PIXEL_TYPE_FLOAT: i32 = 2;
+const NO_COMPRESSION: u8 = 0;
+const INCREASING_Y: u8 = 0;
+
+/// A single named image channel, one value per pixel in scanline order.
+pub struct Channel<'a> {
+    pub name: &'a str,
+    pub data: &'a [f32],
+}
+
+/// Writes an uncompressed scanline OpenEXR image with 32-bit float channels.
+///
+/// Channels may use dotted layer names (`"albedo.R"`) to store several
+/// images in one file.
+pub fn write_exr(
+    out: &mut impl Write,
+    width: usize,
+    height: usize,
+    channels: &[Channel],
+) -> io::Result<()> {
+    let mut channels: Vec<&Channel> = channels.iter().collect();
+    channels.sort_by(|a, b| a.name.cmp(b.name));
+    for channel in &channels {
+        if channel.data.len() != width * height {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("channel {} does not match the image size", channel.name),
+            ));
+        }
+    }
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&MAGIC);
+    header.extend_from_slice(&VERSION.to_le_bytes());
+
+    let mut chlist = Vec::new();
+    for channel in &channels {
+        chlist.extend_from_slice(channel.name.as_bytes());
+        chlist.push(0);
+        chlist.extend_from_slice(&PIXEL_TYPE_FLOAT.to_le_bytes());
+        chlist.extend_from_slice(&[0, 0, 0, 0]);
+        chlist.extend_from_slice(&1i32.to_le_bytes());
+        chlist.extend_from_slice(&1i32.to_le_bytes());
+    }
+    chlist.push(0);
+    attribute(&mut header, "channels", "chlist", &chlist);
+    attribute(&mut header, "compression", "compression", &[NO_COMPRESSION]);
+
+    let window = [0, 0, width as i32 - 1, height as i32 - 1]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect::<Vec<u8>>();
+    attribute(&mut header, "dataWindow", "box2i", &window);
+    attribute(&mut header, "displayWindow", "box2i", &window);
+    attribute(&mut header, "lineOrder", "lineOrder", &[INCREASING_Y]);
+    attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1.0f32.to_le_bytes(),
+    );
+    attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1.0f32.to_le_bytes(),
+    );
+    header.push(0);
+
+    let line_size = channels.len() * width * 4;
+    let chunk_size = 8 + line_size;
+    let first_chunk = header.len() + 8 * height;
+    out.write_all(&header)?;
+    for y in 0..height {
+        out.write_all(&((first_chunk + y * chunk_size) as u64).to_le_bytes())?;
+    }
+
+    let mut line = Vec::with_capacity(line_size);
+    for y in 0..height {
+        line.clear();
+        for channel in &channels {
+            for value in &channel.data[y * width..(y + 1) * width] {
+                line.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        out.write_all(&(y as i32).to_le_bytes())?;
+        out.write_all(&(line_size as i32).to_le_bytes())?;
+        out.write_all(&line)?;
+    }
+
+    Ok(())
+}
+
+fn attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_u64(bytes: &[u8], at: usize) -> u64 {
+        u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap())
+    }
+
+    fn read_i32(bytes: &[u8], at: usize) -> i32 {
+        i32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    fn find(bytes: &[u8], needle: &[u8]) -> Option<usize> {
+        bytes.windows(needle.len()).position(|w| w == needle)
+    }
+
+    #[test]
+    fn test_write_exr() {
+        let red = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+        let green = [0.5; 6];
+        let channels = [
+            Channel {
+                name: "R",
+                data: &red,
+            },
+            Channel {
+                name: "G",
+                data: &green,
+            },
+        ];
+        let mut out = Vec::new();
+        write_exr(&mut out, 3, 2, &channels).unwrap();
+
+        assert!(out[..4] == MAGIC);
+        assert!(read_i32(&out, 4) == VERSION);
+
+        // The channel list is sorted by name: G before R.
+        let chlist = find(&out, b"channels\0chlist\0").unwrap() + 16;
+        assert!(read_i32(&out, chlist) == 2 * (2 + 16) + 1);
+        assert!(out[chlist + 4..chlist + 6] == *b"G\0");
+        assert!(read_i32(&out, chlist + 6) == PIXEL_TYPE_FLOAT);
+        assert!(out[chlist + 22..chlist + 24] == *b"R\0");
+
+        let compression = find(&out, b"compression\0compression\0").unwrap() + 24;
+        assert!(read_i32(&out, compression) == 1 && out[compression + 4] == NO_COMPRESSION);
+        let window = find(&out, b"dataWindow\0box2i\0").unwrap() + 17;
+        assert!(read_i32(&out, window) == 16);
+        let bounds: Vec<i32> = (0..4).map(|k| read_i32(&out, window + 4 + 4 * k)).collect();
+        assert!(bounds == [0, 0, 2, 1]);
+
+        // The offset table follows the header's terminating null byte.
+        let header_end = find(&out, b"screenWindowWidth\0float\0").unwrap() + 24 + 4 + 4 + 1;
+        let line_size = 2 * 3 * 4;
+        assert!(read_u64(&out, header_end) == (header_end + 16) as u64);
+        assert!(read_u64(&out, header_end + 8) == (header_end + 16 + 8 + line_size) as u64);
+
+        let first = header_end + 16;
+        assert!(read_i32(&out, first) == 0 && read_i32(&out, first + 4) == line_size as i32);
+        let value = |at: usize| f32::from_le_bytes(out[at..at + 4].try_into().unwrap());
+        assert!(value(first + 8) == 0.5 && value(first + 8 + 12) == 1.0);
+        let second = first + 8 + line_size;
+        assert!(read_i32(&out, second) == 1 && value(second + 8 + 12) == 4.0);
+        assert!(out.len() == second + 8 + line_size);
+    }
+
+    #[test]
+    fn test_write_exr_rejects_mismatched_channel() {
+        let data = [0.0; 3];
+        let channels = [Channel {
+            name: "Y",
+            data: &data,
+        }];
+        assert!(write_exr(&mut Vec::new(), 2, 2, &channels).is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use std::str::FromStr;
+
+use crate::rtweekend::PI;
+
+/// The shape of a [`Filter`].
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum FilterKind {
+    /// Equal weight across the filter; with radius one half, each pixel
+    /// averages only its own samples.
+    #[default]
+    Box,
+    /// Weight falling linearly to zero at the radius.
+    Tent,
+    /// A Gaussian with standard deviation a third of the radius, shifted to
+    /// reach zero at the radius.
+    Gaussian,
+    /// The Mitchell–Netravali cubic with B = C = 1/3, balancing blur against ringing.
+    Mitchell,
+    /// A sinc windowed by a wider sinc; the sharpest, at the cost of some ringing.
+    Lanczos,
+}
+
+impl FilterKind {
+    /// The radius the filter is usually used at, in pixels.
+    pub fn default_radius(&self) -> f64 {
+        match self {
+            FilterKind::Box => 0.5,
+            FilterKind::Tent => 1.0,
+            FilterKind::Gaussian => 1.5,
+            FilterKind::Mitchell => 2.0,
+            FilterKind::Lanczos => 3.0,
+        }
+    }
+}
+
+impl FromStr for FilterKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "box" => Ok(FilterKind::Box),
+            "tent" => Ok(FilterKind::Tent),
+            "gaussian" => Ok(FilterKind::Gaussian),
+            "mitchell" => Ok(FilterKind::Mitchell),
+            "lanczos" => Ok(FilterKind::Lanczos),
+            _ => Err(format!(
+                "unknown filter '{s}'; expected box, tent, gaussian, mitchell or lanczos"
+            )),
+        }
+    }
+}
+
+/// A pixel reconstruction filter: how much a sample counts towards a pixel
+/// given its offset from the pixel's centre, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Filter {
+    pub kind: FilterKind,
+    /// How far from a pixel's centre its samples are gathered, in pixels.
+    pub radius: f64,
+}
+
+impl Filter {
+    /// The widest radius a filter may have, in pixels. Each sample is added
+    /// to every pixel within the radius, so wider filters only cost time and
+    /// memory.
+    pub const MAX_RADIUS: f64 = 8.0;
+
+    pub fn new(kind: FilterKind) -> Self {
+        Self::with_radius(kind, kind.default_radius())
+    }
+
+    pub fn with_radius(kind: FilterKind, radius: f64) -> Self {
+        Self { kind, radius }
+    }
+
+    /// The weight of a sample `(x, y)` pixels from a pixel's centre.
+    pub fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.evaluate_1d(x) * self.evaluate_1d(y)
+    }
+
+    /// How many pixels beyond its own a sample can reach on each side.
+    pub fn margin(&self) -> i64 {
+        (self.radius - 0.5).ceil().max(0.0) as i64
+    }
+
+    fn evaluate_1d(&self, x: f64) -> f64 {
+        let r = self.radius;
+        // Half-open, so a box of radius one half gives each sample to exactly one pixel.
+        if x < -r || x >= r {
+            return 0.0;
+        }
+        let x = x.abs();
+        match self.kind {
+            FilterKind::Box => 1.0,
+            FilterKind::Tent => r - x,
+            FilterKind::Gaussian => {
+                let sigma = r / 3.0;
+                let gaussian = |x: f64| (-x * x / (2.0 * sigma * sigma)).exp();
+                (gaussian(x) - gaussian(r)).max(0.0)
+            }
+            FilterKind::Mitchell => mitchell_1d(2.0 * x / r, 1.0 / 3.0, 1.0 / 3.0),
+            FilterKind::Lanczos => sinc(x) * sinc(x / r),
+        }
+    }
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self::new(FilterKind::default())
+    }
+}
+
+fn mitchell_1d(x: f64, b: f64, c: f64) -> f64 {
+    if x > 2.0 {
+        0.0
+    } else if x > 1.0 {
+        ((-b - 6.0 * c) * x * x * x
+            + (6.0 * b + 30.0 * c) * x * x
+            + (-12.0 * b - 48.0 * c) * x
+            + (8.0 * b + 24.0 * c))
+            / 6.0
+    } else {
+        ((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+            + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+            + (6.0 - 2.0 * b))
+            / 6.0
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-5 {
+        return 1.0;
+    }
+    (PI * x).sin() / (PI * x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filters_peak_at_centre_and_vanish_at_radius() {
+        for kind in [
+            FilterKind::Box,
+            FilterKind::Tent,
+            FilterKind::Gaussian,
+            FilterKind::Mitchell,
+            FilterKind::Lanczos,
+        ] {
+            let filter = Filter::new(kind);
+            let centre = filter.evaluate(0.0, 0.0);
+            assert!(centre > 0.0);
+            assert!(filter.evaluate(0.3, -0.2) <= centre);
+            assert!(filter.evaluate(filter.radius, 0.0) == 0.0);
+            assert!(filter.evaluate(0.0, -filter.radius - 0.1) == 0.0);
+        }
+    }
+
+    #[test]
+    fn test_box_filter_covers_one_pixel() {
+        let filter = Filter::default();
+        assert!(filter.margin() == 0);
+        assert!(filter.evaluate(-0.5, 0.49) == 1.0);
+        assert!(filter.evaluate(0.5, 0.0) == 0.0);
+        assert!(Filter::new(FilterKind::Mitchell).margin() == 2);
+    }
+}
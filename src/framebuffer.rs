@@ -0,0 +1,179 @@
+use std::collections::BTreeMap;
+
+use crate::aov::Aov;
+use crate::colour::Colour;
+use crate::tonemap;
+
+pub struct FrameBuffer {
+    width: usize,
+    height: usize,
+    sum: Vec<Colour>,
+    weight: Vec<f64>,
+    samples: Vec<u32>,
+}
+
+impl FrameBuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            sum: vec![Colour::default(); width * height],
+            weight: vec![0.0; width * height],
+            samples: vec![0; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn add_sample(&mut self, i: usize, j: usize, colour: Colour, weight: f64) {
+        let index = j * self.width + i;
+        self.sum[index] += colour;
+        self.weight[index] += weight;
+    }
+
+    /// Adds every pixel of `tile` to this buffer, with the tile's top-left
+    /// pixel landing on `(x0, y0)`; pixels falling outside are dropped.
+    pub fn add_tile(&mut self, tile: &FrameBuffer, x0: i64, y0: i64) {
+        for tj in 0..tile.height {
+            let j = y0 + tj as i64;
+            if j < 0 || j >= self.height as i64 {
+                continue;
+            }
+            for ti in 0..tile.width {
+                let i = x0 + ti as i64;
+                if i < 0 || i >= self.width as i64 {
+                    continue;
+                }
+                let (from, to) = (tj * tile.width + ti, j as usize * self.width + i as usize);
+                self.sum[to] += tile.sum[from];
+                self.weight[to] += tile.weight[from];
+                self.samples[to] += tile.samples[from];
+            }
+        }
+    }
+
+    /// Records that `count` camera samples were traced for pixel `(i, j)`.
+    pub fn count_samples(&mut self, i: usize, j: usize, count: u32) {
+        self.samples[j * self.width + i] += count;
+    }
+
+    /// The number of camera samples traced for pixel `(i, j)`.
+    pub fn samples(&self, i: usize, j: usize) -> u32 {
+        self.samples[j * self.width + i]
+    }
+
+    /// An image of how many samples each pixel took, shading from dark blue
+    /// for the fewest through to yellow for the most.
+    pub fn sample_heatmap(&self) -> FrameBuffer {
+        let min = self.samples.iter().copied().min().unwrap_or(0);
+        let max = self.samples.iter().copied().max().unwrap_or(0);
+        let mut heatmap = FrameBuffer::new(self.width, self.height);
+        for j in 0..self.height {
+            for i in 0..self.width {
+                let t = match max > min {
+                    true => (self.samples(i, j) - min) as f64 / (max - min) as f64,
+                    false => 0.0,
+                };
+                heatmap.add_sample(i, j, heat_colour(t), 1.0);
+            }
+        }
+        heatmap
+    }
+
+    /// The linear radiance of pixel `(i, j)`, averaged over every sample added to it.
+    pub fn pixel(&self, i: usize, j: usize) -> Colour {
+        let index = j * self.width + i;
+        match self.weight[index] > 0.0 {
+            true => self.sum[index] / self.weight[index],
+            false => Colour::default(),
+        }
+    }
+
+    /// Pixels in scanline order, top row first.
+    pub fn pixels(&self) -> impl Iterator<Item = Colour> + '_ {
+        (0..self.height).flat_map(move |j| (0..self.width).map(move |i| self.pixel(i, j)))
+    }
+}
+
+/// A rendered image together with the AOVs recorded while rendering it.
+pub struct RenderLayers {
+    pub colour: FrameBuffer,
+    layers: BTreeMap<Aov, FrameBuffer>,
+}
+
+impl RenderLayers {
+    /// Buffers for the colour and for each of `aovs`.
+    pub fn new(width: usize, height: usize, aovs: &[Aov]) -> Self {
+        Self {
+            colour: FrameBuffer::new(width, height),
+            layers: aovs
+                .iter()
+                .map(|&aov| (aov, FrameBuffer::new(width, height)))
+                .collect(),
+        }
+    }
+
+    /// The buffer for `aov`, if it is being recorded.
+    pub fn layer(&self, aov: Aov) -> Option<&FrameBuffer> {
+        self.layers.get(&aov)
+    }
+
+    /// Every recorded AOV and its buffer, in the order of [`Aov`].
+    pub fn layers(&self) -> impl Iterator<Item = (Aov, &FrameBuffer)> {
+        self.layers.iter().map(|(aov, layer)| (*aov, layer))
+    }
+
+    /// Stops recording every AOV for which `keep` is false, dropping its buffer.
+    pub fn retain(&mut self, mut keep: impl FnMut(Aov) -> bool) {
+        self.layers.retain(|aov, _| keep(*aov));
+    }
+
+    /// Adds a sample to the buffer for `aov` as [`FrameBuffer::add_sample`];
+    /// AOVs that are not being recorded ignore it.
+    pub fn add_sample(&mut self, aov: Aov, i: usize, j: usize, value: Colour, weight: f64) {
+        if let Some(layer) = self.layers.get_mut(&aov) {
+            layer.add_sample(i, j, value, weight);
+        }
+    }
+
+    /// Adds every layer of `tile` to the matching layer here, as [`FrameBuffer::add_tile`].
+    pub fn add_tile(&mut self, tile: &RenderLayers, x0: i64, y0: i64) {
+        self.colour.add_tile(&tile.colour, x0, y0);
+        for (aov, layer) in &mut self.layers {
+            if let Some(from) = tile.layers.get(aov) {
+                layer.add_tile(from, x0, y0);
+            }
+        }
+    }
+}
+
+/// A colour ramp over `t` in `[0, 1]`, returned in linear space so that it
+/// shows as the ramp once sRGB encoded.
+fn heat_colour(t: f64) -> Colour {
+    const STOPS: [[f64; 3]; 4] = [
+        [0.05, 0.03, 0.35],
+        [0.10, 0.55, 0.70],
+        [0.95, 0.45, 0.15],
+        [1.00, 0.95, 0.30],
+    ];
+    let x = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f64;
+    let k = (x as usize).min(STOPS.len() - 2);
+    let f = x - k as f64;
+    let (a, b) = (STOPS[k], STOPS[k + 1]);
+    let display = Colour::new(
+        a[0] + f * (b[0] - a[0]),
+        a[1] + f * (b[1] - a[1]),
+        a[2] + f * (b[2] - a[2]),
+    );
+    Colour::new(
+        tonemap::srgb_eotf(display.x()),
+        tonemap::srgb_eotf(display.y()),
+        tonemap::srgb_eotf(display.z()),
+    )
+}
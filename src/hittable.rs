@@ -1,7 +1,9 @@
 use std::sync::Arc;
 
+use crate::aabb::Aabb;
+use crate::colour::Colour;
 use crate::interval::Interval;
-use crate::material::Material;
+use crate::material::{Lambertian, Material};
 use crate::ray::Ray;
 use crate::vec3::{Point3, Vec3};
 
@@ -10,10 +12,31 @@ pub struct HitRecord {
     pub normal: Vec3,
     pub mat: Arc<dyn Material>,
     pub t: f64,
+    pub u: f64,
+    pub v: f64,
     pub front_face: bool,
+    /// The scene object that was hit, numbered from 1; 0 if it was never tagged.
+    pub object_id: u32,
+    /// The scene material that was hit, numbered from 1; 0 if it was never tagged.
+    pub material_id: u32,
 }
 
 impl HitRecord {
+    /// An empty record for a hit test to fill in, with `mat` as its material.
+    pub fn new(mat: Arc<dyn Material>) -> Self {
+        Self {
+            p: Point3::default(),
+            normal: Vec3::default(),
+            mat,
+            t: 0.0,
+            u: 0.0,
+            v: 0.0,
+            front_face: false,
+            object_id: 0,
+            material_id: 0,
+        }
+    }
+
     pub fn set_face_normal(&mut self, r: &Ray, outward_normal: &Vec3) {
         self.front_face = r.direction().dot(*outward_normal) < 0.0;
         self.normal = if self.front_face {
@@ -24,6 +47,43 @@ impl HitRecord {
     }
 }
 
-pub trait Hittable {
+impl Default for HitRecord {
+    fn default() -> Self {
+        HitRecord::new(Arc::new(Lambertian::new(Colour::default())))
+    }
+}
+
+pub trait Hittable: Sync + Send {
     fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool;
+
+    fn bounding_box(&self) -> Aabb;
+
+    /// The solid-angle density with which `random` picks `direction` from
+    /// `origin`, or 0 for objects that cannot be sampled as lights.
+    fn pdf_value(&self, _origin: Point3, _direction: Vec3) -> f64 {
+        0.0
+    }
+
+    /// A direction from `origin` towards the object, warped from the sample
+    /// point `u`, for sampling it as a light.
+    fn random(&self, _origin: Point3, _u: [f64; 2]) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
+}
+
+/// The solid-angle density of `direction` from `origin` for a flat shape of
+/// the given area sampled uniformly by area.
+pub fn area_pdf_value(shape: &dyn Hittable, area: f64, origin: Point3, direction: Vec3) -> f64 {
+    let mut rec = HitRecord::default();
+    if !shape.hit(
+        &Ray::new(origin, direction),
+        Interval::new(0.001, f64::INFINITY),
+        &mut rec,
+    ) {
+        return 0.0;
+    }
+
+    let distance_squared = rec.t * rec.t * direction.length_squared();
+    let cosine = (direction.dot(rec.normal) / direction.length()).abs();
+    distance_squared / (cosine * area)
 }
@@ -1,47 +1,60 @@
 use std::sync::Arc;
 
-use crate::colour::Colour;
+use crate::aabb::Aabb;
 use crate::hittable::{HitRecord, Hittable};
 use crate::interval::Interval;
-use crate::material::Lambertian;
 use crate::ray::Ray;
+use crate::sampler;
 use crate::vec3::{Point3, Vec3};
 
-pub struct HittableList<T: Hittable> {
-    objects: Vec<T>,
+#[derive(Default)]
+pub struct HittableList {
+    objects: Vec<Arc<dyn Hittable>>,
+    bbox: Aabb,
 }
 
-impl<T: Hittable> HittableList<T> {
-    pub fn new(item: T) -> Self {
+impl HittableList {
+    pub fn new(item: Arc<dyn Hittable>) -> Self {
         HittableList {
+            bbox: item.bounding_box(),
             objects: vec![item],
         }
     }
 
-    pub fn add(&mut self, item: T) {
+    pub fn add(&mut self, item: Arc<dyn Hittable>) {
+        self.bbox = Aabb::enclosing(&self.bbox, &item.bounding_box());
         self.objects.push(item);
     }
 
     pub fn clear(&mut self) {
         self.objects.clear();
+        self.bbox = Aabb::EMPTY;
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    pub fn objects(&self) -> &[Arc<dyn Hittable>] {
+        &self.objects
+    }
+
+    pub fn into_objects(self) -> Vec<Arc<dyn Hittable>> {
+        self.objects
     }
 }
 
-impl<T: Hittable> Hittable for HittableList<T> {
+impl Hittable for HittableList {
     fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
         let mut hit_anything: bool = false;
         let mut closest_so_far: f64 = ray_t.max;
 
         for object in &self.objects {
-            let mut temp_rec = HitRecord {
-                mat: Arc::new(Lambertian {
-                    albedo: Colour::default(),
-                }),
-                p: Point3::default(),
-                normal: Vec3::default(),
-                front_face: bool::default(),
-                t: f64::default(),
-            };
+            let mut temp_rec = HitRecord::default();
             if object.hit(r, Interval::new(ray_t.min, closest_so_far), &mut temp_rec) {
                 hit_anything = true;
                 closest_so_far = temp_rec.t;
@@ -51,4 +64,22 @@ impl<T: Hittable> Hittable for HittableList<T> {
 
         hit_anything
     }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    /// The average density over the list, matching `random`'s uniform choice of object.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let weight = 1.0 / self.objects.len() as f64;
+        self.objects
+            .iter()
+            .map(|object| weight * object.pdf_value(origin, direction))
+            .sum()
+    }
+
+    fn random(&self, origin: Point3, u: [f64; 2]) -> Vec3 {
+        let (index, u0) = sampler::split_sample(u[0], self.objects.len());
+        self.objects[index].random(origin, [u0, u[1]])
+    }
 }
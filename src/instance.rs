@@ -0,0 +1,137 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::ray::Ray;
+use crate::transform::Mat4;
+use crate::vec3::{Point3, Vec3};
+
+/// Places a shared object in the world through an affine transform. Rays are
+/// moved into the object's space rather than the object into world space, so
+/// any number of instances can share one object and its acceleration structure.
+pub struct Instance {
+    object: Arc<dyn Hittable>,
+    object_to_world: Mat4,
+    world_to_object: Mat4,
+    bbox: Aabb,
+}
+
+impl Instance {
+    pub fn new(object: Arc<dyn Hittable>, object_to_world: Mat4) -> Self {
+        let world_to_object = object_to_world
+            .inverse()
+            .expect("cannot instance an object through a singular transform");
+
+        let object_box = object.bounding_box();
+        let mut min = Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut max = Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for corner in 0..8 {
+            let p = object_to_world.transform_point(Point3::new(
+                bound(object_box.x, corner & 1),
+                bound(object_box.y, corner & 2),
+                bound(object_box.z, corner & 4),
+            ));
+            for axis in 0..3 {
+                min[axis] = min[axis].min(p[axis]);
+                max[axis] = max[axis].max(p[axis]);
+            }
+        }
+
+        Self {
+            object,
+            object_to_world,
+            world_to_object,
+            bbox: Aabb::from_points(min, max),
+        }
+    }
+}
+
+fn bound(interval: Interval, upper: usize) -> f64 {
+    if upper == 0 {
+        interval.min
+    } else {
+        interval.max
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        // The direction is not renormalised, so `t` means the same in both spaces.
+        let object_ray = Ray::with_time(
+            self.world_to_object.transform_point(r.origin()),
+            self.world_to_object.transform_vector(r.direction()),
+            r.time(),
+        )
+        .with_sample(r.sample());
+        if !self.object.hit(&object_ray, ray_t, rec) {
+            return false;
+        }
+
+        rec.p = self.object_to_world.transform_point(rec.p);
+        // Normals transform by the inverse transpose to stay perpendicular under non-uniform scaling.
+        rec.normal = self
+            .world_to_object
+            .transpose()
+            .transform_vector(rec.normal)
+            .unit_vector();
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let object_direction = self
+            .world_to_object
+            .transform_vector(direction.unit_vector());
+        let object_pdf = self.object.pdf_value(
+            self.world_to_object.transform_point(origin),
+            object_direction,
+        );
+        // Account for the transform stretching the sphere of directions.
+        object_pdf * self.world_to_object.determinant().abs() / object_direction.length().powi(3)
+    }
+
+    fn random(&self, origin: Point3, u: [f64; 2]) -> Vec3 {
+        let object_origin = self.world_to_object.transform_point(origin);
+        self.object_to_world
+            .transform_vector(self.object.random(object_origin, u))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colour::Colour;
+    use crate::material::{Lambertian, Material};
+    use crate::sphere::Sphere;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn test_hit_scaled_and_moved_sphere() {
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(Colour::default()));
+        let sphere: Arc<dyn Hittable> = Arc::new(Sphere::new(Point3::default(), 1.0, mat.clone()));
+        let instance = Instance::new(
+            sphere,
+            Mat4::translation(Vec3::new(0.0, 0.0, -5.0)) * Mat4::scaling(Vec3::new(2.0, 1.0, 1.0)),
+        );
+
+        let bbox = instance.bounding_box();
+        assert!((bbox.x.min + 2.0).abs() < 1e-9 && (bbox.x.max - 2.0).abs() < 1e-9);
+        assert!((bbox.z.min + 6.0).abs() < 1e-9 && (bbox.z.max + 4.0).abs() < 1e-9);
+
+        let mut rec = HitRecord::new(mat);
+        let r = Ray::new(Point3::new(-5.0, 0.0, -5.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(instance.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+        assert!((rec.t - 3.0).abs() < 1e-9);
+        assert!((rec.p - Point3::new(-2.0, 0.0, -5.0)).near_zero());
+        assert!(rec.front_face);
+        assert!((rec.normal - Vec3::new(-1.0, 0.0, 0.0)).near_zero());
+
+        // Off the stretched silhouette's edge: the sphere is only one unit tall.
+        let r = Ray::new(Point3::new(1.5, 1.5, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(!instance.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+    }
+}
@@ -1,15 +1,44 @@
+use std::ops::Add;
+
 use crate::rtweekend::INFINITY;
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Interval {
     pub min: f64,
     pub max: f64,
 }
 
 impl Interval {
+    pub const EMPTY: Interval = Interval {
+        min: INFINITY,
+        max: -INFINITY,
+    };
+
+    pub const UNIVERSE: Interval = Interval {
+        min: -INFINITY,
+        max: INFINITY,
+    };
+
     pub fn new(min: f64, max: f64) -> Self {
         Self { min, max }
     }
 
+    pub fn enclosing(a: Interval, b: Interval) -> Self {
+        Self {
+            min: a.min.min(b.min),
+            max: a.max.max(b.max),
+        }
+    }
+
+    pub fn size(&self) -> f64 {
+        self.max - self.min
+    }
+
+    pub fn expand(&self, delta: f64) -> Interval {
+        let padding = delta / 2.0;
+        Interval::new(self.min - padding, self.max + padding)
+    }
+
     pub fn contains(&self, x: f64) -> bool {
         self.min <= x && x <= self.max
     }
@@ -31,18 +60,16 @@ impl Interval {
     }
 }
 
-const EMPTY: Interval = Interval {
-    min: INFINITY,
-    max: -INFINITY,
-};
-
-const UNIVERSE: Interval = Interval {
-    min: -INFINITY,
-    max: INFINITY,
-};
-
 impl Default for Interval {
     fn default() -> Self {
-        EMPTY
+        Interval::EMPTY
+    }
+}
+
+impl Add<f64> for Interval {
+    type Output = Interval;
+
+    fn add(self, displacement: f64) -> Interval {
+        Interval::new(self.min + displacement, self.max + displacement)
     }
 }
@@ -0,0 +1,35 @@
+pub mod aabb;
+pub mod aov;
+pub mod bvh;
+pub mod camera;
+pub mod colour;
+pub mod constant_medium;
+pub mod denoise;
+pub mod disk;
+pub mod exr;
+pub mod filter;
+pub mod framebuffer;
+pub mod hittable;
+pub mod hittable_list;
+pub mod instance;
+pub mod interval;
+pub mod material;
+pub mod mesh;
+pub mod motion;
+pub mod obj;
+pub mod output;
+pub mod pdf;
+pub mod perlin;
+pub mod procedural;
+pub mod quad;
+pub mod ray;
+pub mod rtweekend;
+pub mod sampler;
+pub mod scene;
+pub mod sphere;
+pub mod tagged;
+pub mod texture;
+pub mod tonemap;
+pub mod transform;
+pub mod triangle;
+pub mod vec3;
@@ -1,91 +1,532 @@
-use std::sync::Arc;
-
-mod camera;
-mod colour;
-mod hittable;
-mod hittable_list;
-mod interval;
-mod material;
-mod ray;
-mod rtweekend;
-mod sphere;
-mod vec3;
-
-fn main() {
-    let ground_material = Arc::new(material::Lambertian {
-        albedo: colour::Colour::new(0.5, 0.5, 0.5),
-    });
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Instant;
+
+use clap::{Args, Parser, Subcommand};
+
+use ray_tracing_one_weekend::aov::Aov;
+use ray_tracing_one_weekend::denoise::Denoiser;
+use ray_tracing_one_weekend::filter::{Filter, FilterKind};
+use ray_tracing_one_weekend::hittable::Hittable;
+use ray_tracing_one_weekend::output::{self, ImageFormat};
+use ray_tracing_one_weekend::sampler::SamplerKind;
+use ray_tracing_one_weekend::scene::{self, Scene};
+use ray_tracing_one_weekend::tonemap::ToneOperator;
+use ray_tracing_one_weekend::vec3::Vec3;
+
+#[derive(Parser)]
+#[command(version, about = "Ray Tracing in One Weekend, in Rust")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Render a scene to an image file.
+    Render {
+        #[command(flatten)]
+        settings: SceneArgs,
+
+        /// Output image path; defaults to the scene's `output` setting or image.png.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Output format, overriding the one implied by the output extension.
+        #[arg(short, long, value_parser = parse_format)]
+        format: Option<ImageFormat>,
+
+        /// Also write an image of how many samples each pixel took.
+        #[arg(long)]
+        heatmap: Option<PathBuf>,
+
+        /// Write each AOV to its own image even when the output is EXR,
+        /// which otherwise holds them as layers.
+        #[arg(long)]
+        split_aovs: bool,
+    },
+    /// Print the settings a scene would be rendered with.
+    Info {
+        #[command(flatten)]
+        settings: SceneArgs,
+    },
+    /// Render a scene repeatedly without writing it and report throughput.
+    Bench {
+        #[command(flatten)]
+        settings: SceneArgs,
+
+        /// Number of timed renders.
+        #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..))]
+        iterations: u32,
+    },
+}
+
+#[derive(Args)]
+struct SceneArgs {
+    /// TOML scene file; renders the first book's final scene when omitted.
+    scene: Option<PathBuf>,
+
+    /// Image width in pixels.
+    #[arg(long, value_parser = clap::value_parser!(i64).range(1..))]
+    width: Option<i64>,
+
+    /// Image height in pixels; with --width this sets the aspect ratio.
+    #[arg(long, value_parser = clap::value_parser!(i64).range(1..))]
+    height: Option<i64>,
+
+    /// Samples per pixel; the most any pixel takes with adaptive sampling.
+    #[arg(short, long, value_parser = clap::value_parser!(i32).range(1..))]
+    samples: Option<i32>,
+
+    /// Sample adaptively, stopping pixels once their relative noise is below this.
+    #[arg(long, value_parser = parse_positive)]
+    adaptive_threshold: Option<f64>,
+
+    /// Samples every pixel takes before adaptive sampling may stop it.
+    #[arg(long, value_parser = clap::value_parser!(i32).range(2..))]
+    min_samples: Option<i32>,
+
+    /// Maximum number of bounces per path.
+    #[arg(long, value_parser = clap::value_parser!(i32).range(1..))]
+    max_depth: Option<i32>,
+
+    /// Bounces before paths may be ended early by Russian roulette.
+    #[arg(long, value_parser = clap::value_parser!(i32).range(0..))]
+    roulette_depth: Option<i32>,
+
+    /// Seed for the random number generator.
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Sample pattern: independent, stratified, halton, sobol or blue-noise.
+    #[arg(long, value_parser = parse_sampler)]
+    sampler: Option<SamplerKind>,
+
+    /// Pixel reconstruction filter: box, tent, gaussian, mitchell or lanczos.
+    #[arg(long, value_parser = parse_filter)]
+    filter: Option<FilterKind>,
+
+    /// Filter radius in pixels, up to 8; defaults to the usual radius for the filter.
+    #[arg(long, value_parser = parse_filter_radius)]
+    filter_radius: Option<f64>,
+
+    /// Number of render threads; defaults to one per core.
+    #[arg(short, long, value_parser = clap::value_parser!(u64).range(1..))]
+    threads: Option<u64>,
+
+    /// Exposure adjustment in stops.
+    #[arg(long, value_parser = parse_number, allow_hyphen_values = true)]
+    exposure: Option<f64>,
+
+    /// Colour temperature in kelvin to render as neutral white.
+    #[arg(long, value_parser = parse_positive)]
+    white_balance: Option<f64>,
+
+    /// Tone operator: clamp, reinhard, extended-reinhard, aces or agx.
+    #[arg(long, value_parser = parse_tone_map)]
+    tone_map: Option<ToneOperator>,
+
+    /// Luminance that extended Reinhard maps to white.
+    #[arg(long, value_parser = parse_positive)]
+    white_point: Option<f64>,
+
+    /// Denoise the render, guided by the albedo, normals and depth it saw.
+    #[arg(long)]
+    denoise: bool,
+
+    /// Also render a pass of albedo, normal, depth, position, object-id,
+    /// material-id, direct, indirect or variance; repeat for more.
+    #[arg(long = "aov", value_parser = parse_aov)]
+    aovs: Vec<Aov>,
+
+    /// Camera position, as x,y,z.
+    #[arg(long, value_parser = parse_vec3, allow_hyphen_values = true)]
+    look_from: Option<Vec3>,
 
-    let mut world = hittable_list::HittableList::new(sphere::Sphere::new(
-        vec3::Point3::new(0.0, -1000.0, 0.0),
-        1000.0,
-        ground_material,
-    ));
-
-    for a in -11..11 {
-        for b in -11..11 {
-            let choose_mat = rtweekend::random_float();
-            let center = vec3::Point3::new(
-                a as f64 + 0.9 * rtweekend::random_float(),
-                0.2,
-                b as f64 + 0.9 * rtweekend::random_float(),
-            );
-
-            if (center - vec3::Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
-                if choose_mat < 0.8 {
-                    let albedo = colour::Colour::random() * colour::Colour::random();
-                    let sphere_material = Arc::new(material::Lambertian { albedo });
-                    world.add(sphere::Sphere::new(center, 0.2, sphere_material));
-                } else if choose_mat < 0.95 {
-                    let albedo = colour::Colour::random_range(0.5, 1.0);
-                    let fuzz = rtweekend::random_float_range(0.0, 0.5);
-                    let sphere_material = Arc::new(material::Metal { albedo, fuzz });
-                    world.add(sphere::Sphere::new(center, 0.2, sphere_material));
-                } else {
-                    let sphere_material = Arc::new(material::Dielectric { ir: 1.5 });
-                    world.add(sphere::Sphere::new(center, 0.2, sphere_material));
+    /// Point the camera looks at, as x,y,z.
+    #[arg(long, value_parser = parse_vec3, allow_hyphen_values = true)]
+    look_at: Option<Vec3>,
+
+    /// Vertical field of view in degrees.
+    #[arg(long, value_parser = parse_vfov)]
+    vfov: Option<f64>,
+
+    /// Angle of the cone of rays through each pixel, in degrees; 0 disables depth of field.
+    #[arg(long, value_parser = parse_non_negative)]
+    defocus_angle: Option<f64>,
+
+    /// Distance from the camera to the plane of perfect focus.
+    #[arg(long, value_parser = parse_positive)]
+    focus_dist: Option<f64>,
+}
+
+impl SceneArgs {
+    fn load(&self) -> Result<Scene, String> {
+        let mut scene = match &self.scene {
+            Some(path) => scene::load_scene(path, &self.aovs)
+                .map_err(|err| format!("unable to load {}: {err}", path.display()))?,
+            None => scene::random_spheres(self.seed.unwrap_or(0), &self.aovs),
+        };
+
+        let cam = &mut scene.camera;
+        match (self.width, self.height) {
+            (Some(width), Some(height)) => {
+                cam.image_width = width;
+                cam.aspect_ratio = width as f64 / height as f64;
+            }
+            (Some(width), None) => cam.image_width = width,
+            (None, Some(height)) => {
+                cam.image_width = (height as f64 * cam.aspect_ratio).round().max(1.0) as i64
+            }
+            (None, None) => {}
+        }
+        if let Some(samples) = self.samples {
+            cam.samples_per_pixel = samples;
+        }
+        if let Some(threshold) = self.adaptive_threshold {
+            cam.adaptive_threshold = Some(threshold);
+        }
+        if let Some(min_samples) = self.min_samples {
+            cam.min_samples = min_samples;
+        }
+        if let Some(max_depth) = self.max_depth {
+            cam.max_depth = max_depth;
+        }
+        if let Some(roulette_depth) = self.roulette_depth {
+            cam.roulette_depth = roulette_depth;
+        }
+        if let Some(seed) = self.seed {
+            cam.seed = seed;
+        }
+        if let Some(sampler) = self.sampler {
+            cam.sampler = sampler;
+        }
+        match (self.filter, self.filter_radius) {
+            (Some(kind), Some(radius)) => cam.filter = Filter::with_radius(kind, radius),
+            (Some(kind), None) => cam.filter = Filter::new(kind),
+            (None, Some(radius)) => cam.filter.radius = radius,
+            (None, None) => {}
+        }
+        if let Some(threads) = self.threads {
+            cam.threads = Some(threads as usize);
+        }
+        if let Some(look_from) = self.look_from {
+            cam.look_from = look_from;
+        }
+        if let Some(look_at) = self.look_at {
+            cam.look_at = look_at;
+        }
+        if let Some(vfov) = self.vfov {
+            cam.vfov = vfov;
+        }
+        if let Some(defocus_angle) = self.defocus_angle {
+            cam.defocus_angle = defocus_angle;
+        }
+        if let Some(focus_dist) = self.focus_dist {
+            cam.focus_dist = focus_dist;
+        }
+
+        let tonemap = &mut scene.tonemap;
+        if let Some(exposure) = self.exposure {
+            tonemap.exposure = exposure;
+        }
+        if let Some(kelvin) = self.white_balance {
+            tonemap.set_white_balance(Some(kelvin));
+        }
+        if let Some(operator) = self.tone_map {
+            tonemap.operator = operator;
+        }
+        if let Some(white_point) = self.white_point {
+            match &mut tonemap.operator {
+                ToneOperator::ExtendedReinhard { white } => *white = white_point,
+                _ => {
+                    return Err(String::from(
+                        "--white-point needs --tone-map extended-reinhard",
+                    ))
                 }
             }
         }
+
+        if self.denoise {
+            scene.denoiser.get_or_insert_with(Denoiser::default);
+        }
+
+        let cam = &scene.camera;
+        if cam.image_width < 1 || cam.aspect_ratio.is_nan() || cam.aspect_ratio <= 0.0 {
+            return Err(String::from("the image must have a positive size"));
+        }
+        if cam.samples_per_pixel < 1 || cam.max_depth < 1 {
+            return Err(String::from(
+                "the samples per pixel and max depth must be greater than 0",
+            ));
+        }
+        if (cam.look_from - cam.look_at).near_zero() {
+            return Err(String::from("the camera cannot look at its own position"));
+        }
+        Ok(scene)
     }
+}
+
+fn parse_format(s: &str) -> Result<ImageFormat, String> {
+    s.parse()
+}
 
-    let material1 = Arc::new(material::Dielectric { ir: 1.5 });
-    world.add(sphere::Sphere::new(
-        vec3::Point3::new(0.0, 1.0, 0.0),
-        1.0,
-        material1,
-    ));
+fn parse_sampler(s: &str) -> Result<SamplerKind, String> {
+    s.parse()
+}
 
-    let material2 = Arc::new(material::Lambertian {
-        albedo: colour::Colour::new(0.4, 0.2, 0.1),
-    });
-    world.add(sphere::Sphere::new(
-        vec3::Point3::new(-4.0, 1.0, 0.0),
-        1.0,
-        material2,
-    ));
-
-    let material3 = Arc::new(material::Metal {
-        albedo: colour::Colour::new(0.7, 0.6, 0.5),
-        fuzz: 0.0,
+fn parse_filter(s: &str) -> Result<FilterKind, String> {
+    s.parse()
+}
+
+fn parse_tone_map(s: &str) -> Result<ToneOperator, String> {
+    s.parse()
+}
+
+fn parse_aov(s: &str) -> Result<Aov, String> {
+    s.parse()
+}
+
+fn parse_vec3(s: &str) -> Result<Vec3, String> {
+    let values = s
+        .split(',')
+        .map(|v| {
+            v.trim()
+                .parse::<f64>()
+                .map_err(|_| format!("'{v}' is not a number"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    match values[..] {
+        [x, y, z] => Ok(Vec3::new(x, y, z)),
+        _ => Err(format!(
+            "expected three comma-separated values, found {}",
+            values.len()
+        )),
+    }
+}
+
+fn parse_number(s: &str) -> Result<f64, String> {
+    s.parse::<f64>()
+        .ok()
+        .filter(|v| v.is_finite())
+        .ok_or_else(|| format!("'{s}' is not a number"))
+}
+
+fn parse_vfov(s: &str) -> Result<f64, String> {
+    let vfov = parse_number(s)?;
+    if vfov <= 0.0 || vfov >= 180.0 {
+        return Err(String::from("must be between 0 and 180 degrees"));
+    }
+    Ok(vfov)
+}
+
+fn parse_non_negative(s: &str) -> Result<f64, String> {
+    let value = parse_number(s)?;
+    if value < 0.0 {
+        return Err(String::from("must not be negative"));
+    }
+    Ok(value)
+}
+
+fn parse_positive(s: &str) -> Result<f64, String> {
+    let value = parse_number(s)?;
+    if value <= 0.0 {
+        return Err(String::from("must be greater than 0"));
+    }
+    Ok(value)
+}
+
+fn parse_filter_radius(s: &str) -> Result<f64, String> {
+    let radius = parse_positive(s)?;
+    if radius > Filter::MAX_RADIUS {
+        return Err(format!("must be at most {}", Filter::MAX_RADIUS));
+    }
+    Ok(radius)
+}
+
+fn render(
+    settings: &SceneArgs,
+    output: Option<PathBuf>,
+    format: Option<ImageFormat>,
+    heatmap: Option<PathBuf>,
+    split_aovs: bool,
+) -> Result<(), String> {
+    let mut scene = settings.load()?;
+    let output_path = output
+        .or(scene.output.take())
+        .unwrap_or_else(|| PathBuf::from("image.png"));
+    let format = match format {
+        Some(format) => format,
+        None => ImageFormat::from_path(&output_path).ok_or_else(|| {
+            format!(
+                "cannot tell the image format of {}; use --format",
+                output_path.display()
+            )
+        })?,
+    };
+
+    // The denoiser's guides are rendered too, but only the AOVs asked for are written.
+    let aovs = scene.camera.aovs.clone();
+    if scene.denoiser.is_some() {
+        for aov in Denoiser::AOVS {
+            if !scene.camera.aovs.contains(&aov) {
+                scene.camera.aovs.push(aov);
+            }
+        }
+    }
+    let mut layers = scene.camera.render_layers(&scene.world, &scene.lights);
+    let denoised = scene.denoiser.as_ref().map(|denoiser| {
+        eprintln!("Denoising...");
+        denoiser.denoise(&layers)
     });
-    world.add(sphere::Sphere::new(
-        vec3::Point3::new(4.0, 1.0, 0.0),
-        1.0,
-        material3,
-    ));
-
-    let mut cam = camera::Camera::new(
-        16.0 / 9.0,
-        1200,
-        500,
-        50,
-        20.0,
-        vec3::Point3::new(13.0, 2.0, 3.0),
-        vec3::Point3::new(0.0, 0.0, 0.0),
-        vec3::Vec3::new(0.0, 1.0, 0.0),
-        0.6,
-        10.0,
+    layers.retain(|aov| aovs.contains(&aov));
+    let image = denoised.as_ref().unwrap_or(&layers.colour);
+
+    let layered = format == ImageFormat::Exr && !split_aovs && !aovs.is_empty();
+    let written = match layered {
+        true => output::write_layered_exr(&output_path, image, &layers),
+        false => output::write_image_as(&output_path, image, format, &scene.tonemap),
+    };
+    written.map_err(|err| format!("unable to write {}: {err}", output_path.display()))?;
+    eprintln!("Wrote {}", output_path.display());
+
+    if !layered {
+        for (aov, layer) in layers.layers() {
+            let path = aov_path(&output_path, aov);
+            output::write_aov_as(&path, aov, layer, format, &scene.tonemap)
+                .map_err(|err| format!("unable to write {}: {err}", path.display()))?;
+            eprintln!("Wrote {}", path.display());
+        }
+    }
+
+    if let Some(heatmap_path) = heatmap {
+        output::write_image(&heatmap_path, &image.sample_heatmap())
+            .map_err(|err| format!("unable to write {}: {err}", heatmap_path.display()))?;
+        eprintln!("Wrote {}", heatmap_path.display());
+    }
+    Ok(())
+}
+
+/// Where the `aov` pass of an image written to `output` goes: beside it, as
+/// `<name>.<aov>.<extension>`.
+fn aov_path(output: &Path, aov: Aov) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match output.extension() {
+        Some(extension) => format!("{stem}.{}.{}", aov.name(), extension.to_string_lossy()),
+        None => format!("{stem}.{}", aov.name()),
+    };
+    output.with_file_name(name)
+}
+
+fn info(settings: &SceneArgs) -> Result<(), String> {
+    let scene = settings.load()?;
+    let cam = &scene.camera;
+    let bounds = scene.world.bounding_box();
+
+    println!(
+        "Resolution:      {}x{}",
+        cam.image_width,
+        cam.output_height()
+    );
+    println!("Samples/pixel:   {}", cam.samples_per_pixel);
+    if let Some(threshold) = cam.adaptive_threshold {
+        println!(
+            "Adaptive:        threshold {threshold}, at least {} samples",
+            cam.min_samples
+        );
+    }
+    println!("Max depth:       {}", cam.max_depth);
+    println!("Roulette depth:  {}", cam.roulette_depth);
+    println!("Seed:            {}", cam.seed);
+    println!("Sampler:         {:?}", cam.sampler);
+    println!(
+        "Filter:          {:?}, radius {}",
+        cam.filter.kind, cam.filter.radius
+    );
+    println!("Look from:       {}", cam.look_from);
+    println!("Look at:         {}", cam.look_at);
+    println!("Vertical FOV:    {}", cam.vfov);
+    println!("Defocus angle:   {}", cam.defocus_angle);
+    println!("Focus distance:  {}", cam.focus_dist);
+    println!(
+        "Shutter:         [{}, {}]",
+        cam.shutter_open, cam.shutter_close
     );
-    cam.render(&world);
+    println!(
+        "World bounds:    [{}, {}] x [{}, {}] x [{}, {}]",
+        bounds.x.min, bounds.x.max, bounds.y.min, bounds.y.max, bounds.z.min, bounds.z.max
+    );
+    let tonemap = &scene.tonemap;
+    println!("Exposure:        {} EV", tonemap.exposure);
+    if let Some(kelvin) = tonemap.white_balance() {
+        println!("White balance:   {kelvin} K");
+    }
+    println!("Tone map:        {:?}", tonemap.operator);
+    println!("Denoise:         {}", scene.denoiser.is_some());
+    if !cam.aovs.is_empty() {
+        let names: Vec<&str> = cam.aovs.iter().map(Aov::name).collect();
+        println!("AOVs:            {}", names.join(", "));
+    }
+    if let Some(output) = &scene.output {
+        println!("Output:          {}", output.display());
+    }
+    Ok(())
+}
+
+fn bench(settings: &SceneArgs, iterations: u32) -> Result<(), String> {
+    let mut scene = settings.load()?;
+
+    let mut times = Vec::new();
+    let mut samples = 0.0;
+    for iteration in 1..=iterations {
+        let start = Instant::now();
+        let image = scene.camera.render(&scene.world, &scene.lights);
+        let elapsed = start.elapsed().as_secs_f64();
+        eprintln!("Iteration {iteration}: {elapsed:.3}s");
+        times.push(elapsed);
+        samples = (0..image.height())
+            .flat_map(|j| (0..image.width()).map(move |i| (i, j)))
+            .map(|(i, j)| image.samples(i, j) as f64)
+            .sum();
+    }
+
+    let best = times.iter().copied().fold(f64::INFINITY, f64::min);
+    let mean = times.iter().sum::<f64>() / times.len() as f64;
+    println!("Best:   {best:.3}s");
+    println!("Mean:   {mean:.3}s");
+    println!("Speed:  {:.3} Msamples/s", samples / best / 1e6);
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let result = match &cli.command {
+        Command::Render {
+            settings,
+            output,
+            format,
+            heatmap,
+            split_aovs,
+        } => render(
+            settings,
+            output.clone(),
+            *format,
+            heatmap.clone(),
+            *split_aovs,
+        ),
+        Command::Info { settings } => info(settings),
+        Command::Bench {
+            settings,
+            iterations,
+        } => bench(settings, *iterations),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
 }
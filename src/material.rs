@@ -1,49 +1,89 @@
+use std::sync::Arc;
+
 use crate::colour::Colour;
 use crate::hittable::HitRecord;
+use crate::pdf::{CosinePdf, Pdf, SpherePdf};
 use crate::ray::Ray;
 use crate::rtweekend;
+use crate::sampler;
+use crate::texture::{SolidColour, Texture};
 use crate::vec3::Vec3;
 
+/// The outcome of a material scattering a ray.
+pub enum ScatterRecord {
+    /// Scattering along a ray chosen by the material with no density to
+    /// weigh it by, such as a mirror reflection. `attenuation` is the fraction
+    /// of the light carried back along it.
+    Specular { attenuation: Colour, ray: Ray },
+    /// Scattering into a spread of directions, drawn from `pdf` and weighted
+    /// by [`Material::bsdf`].
+    Diffuse { pdf: Box<dyn Pdf> },
+}
+
 pub trait Material: Sync + Send {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Colour,
-        scattered: &mut Ray,
-    ) -> bool;
+    /// How `r_in` scatters at `rec`, or `None` if it is absorbed. Materials
+    /// that pick their scattered ray at random draw it from the sample `u`.
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, u: [f64; 2]) -> Option<ScatterRecord>;
+
+    fn emitted(&self, _r_in: &Ray, _rec: &HitRecord) -> Colour {
+        Colour::new(0.0, 0.0, 0.0)
+    }
+
+    /// The fraction of light arriving from `direction` that leaves along
+    /// `r_in` reversed, times the cosine at the surface, for materials that
+    /// scatter diffusely.
+    fn bsdf(&self, _r_in: &Ray, _rec: &HitRecord, _direction: Vec3) -> Colour {
+        Colour::new(0.0, 0.0, 0.0)
+    }
+
+    /// The surface's own colour at `rec`, which guides the denoiser.
+    fn albedo(&self, _rec: &HitRecord) -> Colour {
+        Colour::new(1.0, 1.0, 1.0)
+    }
 }
 
 pub struct Lambertian {
-    pub albedo: Colour,
+    pub albedo: Arc<dyn Texture>,
+}
+
+impl Lambertian {
+    pub fn new(albedo: Colour) -> Self {
+        Lambertian::from_texture(Arc::new(SolidColour::new(albedo)))
+    }
+
+    pub fn from_texture(albedo: Arc<dyn Texture>) -> Self {
+        Self { albedo }
+    }
 }
 
 impl Material for Lambertian {
-    fn scatter(
-        &self,
-        _r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Colour,
-        scattered: &mut Ray,
-    ) -> bool {
-        let mut scatter_direction = rec.normal + Vec3::random_unit_vector();
-        if scatter_direction.near_zero() {
-            scatter_direction = rec.normal;
-        }
+    fn scatter(&self, _r_in: &Ray, rec: &HitRecord, _u: [f64; 2]) -> Option<ScatterRecord> {
+        Some(ScatterRecord::Diffuse {
+            pdf: Box::new(CosinePdf::new(rec.normal)),
+        })
+    }
 
-        *scattered = Ray::new(rec.p, scatter_direction);
-        *attenuation = self.albedo;
-        true
+    fn bsdf(&self, _r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Colour {
+        let cos_theta = rec.normal.dot(direction.unit_vector()).max(0.0);
+        cos_theta / rtweekend::PI * self.albedo.value(rec.u, rec.v, &rec.p)
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Colour {
+        self.albedo.value(rec.u, rec.v, &rec.p)
     }
 }
 
 pub struct Metal {
-    pub albedo: Colour,
+    pub albedo: Arc<dyn Texture>,
     pub fuzz: f64,
 }
 
 impl Metal {
     pub fn new(albedo: Colour, f: f64) -> Self {
+        Metal::from_texture(Arc::new(SolidColour::new(albedo)), f)
+    }
+
+    pub fn from_texture(albedo: Arc<dyn Texture>, f: f64) -> Self {
         Self {
             albedo,
             fuzz: if f < 1.0 { f } else { 1.0 },
@@ -52,18 +92,21 @@ impl Metal {
 }
 
 impl Material for Metal {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Colour,
-        scattered: &mut Ray,
-    ) -> bool {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, u: [f64; 2]) -> Option<ScatterRecord> {
         let reflected = Vec3::reflect(r_in.direction().unit_vector(), rec.normal);
+        let direction = reflected + self.fuzz * sampler::sample_uniform_sphere(u);
+        if direction.dot(rec.normal) <= 0.0 {
+            return None;
+        }
+
+        Some(ScatterRecord::Specular {
+            attenuation: self.albedo.value(rec.u, rec.v, &rec.p),
+            ray: Ray::with_time(rec.p, direction, r_in.time()),
+        })
+    }
 
-        *scattered = Ray::new(rec.p, reflected + self.fuzz * Vec3::random_unit_vector());
-        *attenuation = self.albedo;
-        scattered.direction().dot(rec.normal) > 0.0
+    fn albedo(&self, rec: &HitRecord) -> Colour {
+        self.albedo.value(rec.u, rec.v, &rec.p)
     }
 }
 
@@ -80,14 +123,7 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Colour,
-        scattered: &mut Ray,
-    ) -> bool {
-        *attenuation = Colour::new(1.0, 1.0, 1.0);
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, u: [f64; 2]) -> Option<ScatterRecord> {
         let refraction_ratio = if rec.front_face {
             1.0 / self.ir
         } else {
@@ -99,14 +135,70 @@ impl Material for Dielectric {
         let sin_theta = (1.0_f64 - cos_theta.powf(2.0)).sqrt();
 
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
-        let direction = match cannot_refract
-            | (Dielectric::reflectance(cos_theta, refraction_ratio) > rtweekend::random_float())
-        {
-            true => Vec3::reflect(unit_direction, rec.normal),
-            false => Vec3::refract(unit_direction, rec.normal, refraction_ratio),
-        };
+        let direction =
+            match cannot_refract | (Dielectric::reflectance(cos_theta, refraction_ratio) > u[0]) {
+                true => Vec3::reflect(unit_direction, rec.normal),
+                false => Vec3::refract(unit_direction, rec.normal, refraction_ratio),
+            };
+
+        Some(ScatterRecord::Specular {
+            attenuation: Colour::new(1.0, 1.0, 1.0),
+            ray: Ray::with_time(rec.p, direction, r_in.time()),
+        })
+    }
+}
+
+pub struct DiffuseLight {
+    pub emit: Arc<dyn Texture>,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Colour) -> Self {
+        DiffuseLight::from_texture(Arc::new(SolidColour::new(emit)))
+    }
+
+    pub fn from_texture(emit: Arc<dyn Texture>) -> Self {
+        Self { emit }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _u: [f64; 2]) -> Option<ScatterRecord> {
+        None
+    }
+
+    fn emitted(&self, _r_in: &Ray, rec: &HitRecord) -> Colour {
+        self.emit.value(rec.u, rec.v, &rec.p)
+    }
+}
+
+/// Scatters light equally in every direction, for the inside of participating media.
+pub struct Isotropic {
+    albedo: Arc<dyn Texture>,
+}
+
+impl Isotropic {
+    pub fn new(albedo: Colour) -> Self {
+        Isotropic::from_texture(Arc::new(SolidColour::new(albedo)))
+    }
+
+    pub fn from_texture(albedo: Arc<dyn Texture>) -> Self {
+        Self { albedo }
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _u: [f64; 2]) -> Option<ScatterRecord> {
+        Some(ScatterRecord::Diffuse {
+            pdf: Box::new(SpherePdf),
+        })
+    }
+
+    fn bsdf(&self, _r_in: &Ray, rec: &HitRecord, _direction: Vec3) -> Colour {
+        self.albedo.value(rec.u, rec.v, &rec.p) / (4.0 * rtweekend::PI)
+    }
 
-        *scattered = Ray::new(rec.p, direction);
-        true
+    fn albedo(&self, rec: &HitRecord) -> Colour {
+        self.albedo.value(rec.u, rec.v, &rec.p)
     }
 }
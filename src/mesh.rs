@@ -0,0 +1,213 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::bvh::BvhNode;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+/// Vertex buffers shared by every triangle of a mesh.
+#[derive(Debug, Default)]
+pub struct MeshData {
+    pub positions: Vec<Point3>,
+    pub normals: Vec<Vec3>,
+    pub texcoords: Vec<(f64, f64)>,
+    pub faces: Vec<MeshFace>,
+    pub groups: Vec<String>,
+}
+
+/// A triangle given as indices into the [`MeshData`] buffers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshFace {
+    pub positions: [usize; 3],
+    pub texcoords: Option<[usize; 3]>,
+    pub normals: Option<[usize; 3]>,
+    pub group: usize,
+}
+
+pub struct Mesh {
+    data: Arc<MeshData>,
+    bvh: BvhNode,
+}
+
+impl Mesh {
+    pub fn new(data: MeshData, mat: Arc<dyn Material>) -> Self {
+        Mesh::with_group_materials(data, |_| mat.clone())
+    }
+
+    /// Builds a mesh whose faces take their material from the name of the group they belong to.
+    pub fn with_group_materials(
+        data: MeshData,
+        material_for_group: impl Fn(&str) -> Arc<dyn Material>,
+    ) -> Self {
+        assert!(!data.faces.is_empty(), "cannot build a mesh with no faces");
+
+        let materials: Vec<Arc<dyn Material>> = data
+            .groups
+            .iter()
+            .map(|group| material_for_group(group))
+            .collect();
+
+        let data = Arc::new(data);
+        let triangles = (0..data.faces.len())
+            .map(|face| {
+                Arc::new(MeshTriangle::new(
+                    data.clone(),
+                    face,
+                    materials[data.faces[face].group].clone(),
+                )) as Arc<dyn Hittable>
+            })
+            .collect();
+
+        Self {
+            data,
+            bvh: BvhNode::new(triangles),
+        }
+    }
+
+    pub fn data(&self) -> &MeshData {
+        &self.data
+    }
+}
+
+impl Hittable for Mesh {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        self.bvh.hit(r, ray_t, rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bvh.bounding_box()
+    }
+}
+
+struct MeshTriangle {
+    data: Arc<MeshData>,
+    face: usize,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+
+impl MeshTriangle {
+    fn new(data: Arc<MeshData>, face: usize, mat: Arc<dyn Material>) -> Self {
+        let [a, b, c] = data.faces[face].positions.map(|i| data.positions[i]);
+        let bbox = Aabb::enclosing(&Aabb::from_points(a, b), &Aabb::from_points(a, c));
+        Self {
+            data,
+            face,
+            mat,
+            bbox,
+        }
+    }
+}
+
+impl Hittable for MeshTriangle {
+    // Möller–Trumbore intersection, which yields the barycentric coordinates used to
+    // interpolate vertex normals.
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let face = &self.data.faces[self.face];
+        let [p0, p1, p2] = face.positions.map(|i| self.data.positions[i]);
+
+        let edge1 = p1 - p0;
+        let edge2 = p2 - p0;
+        let pvec = r.direction().cross(edge2);
+        let det = edge1.dot(pvec);
+        if det.abs() < 1e-12 {
+            return false;
+        }
+
+        let inv_det = 1.0 / det;
+        let tvec = r.origin() - p0;
+        let b1 = tvec.dot(pvec) * inv_det;
+        if !(0.0..=1.0).contains(&b1) {
+            return false;
+        }
+
+        let qvec = tvec.cross(edge1);
+        let b2 = r.direction().dot(qvec) * inv_det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return false;
+        }
+
+        let t = edge2.dot(qvec) * inv_det;
+        if !ray_t.surrounds(t) {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = r.at(t);
+        (rec.u, rec.v) = match face.texcoords {
+            Some([t0, t1, t2]) => {
+                let [uv0, uv1, uv2] = [t0, t1, t2].map(|i| self.data.texcoords[i]);
+                (
+                    (1.0 - b1 - b2) * uv0.0 + b1 * uv1.0 + b2 * uv2.0,
+                    (1.0 - b1 - b2) * uv0.1 + b1 * uv1.1 + b2 * uv2.1,
+                )
+            }
+            None => (b1, b2),
+        };
+        rec.mat = self.mat.clone();
+        let outward_normal = edge1.cross(edge2).unit_vector();
+        rec.set_face_normal(r, &outward_normal);
+
+        if let Some([n0, n1, n2]) = face.normals {
+            let mut shading_normal = ((1.0 - b1 - b2) * self.data.normals[n0]
+                + b1 * self.data.normals[n1]
+                + b2 * self.data.normals[n2])
+                .unit_vector();
+            // Vertex normals don't always agree with the winding order.
+            if shading_normal.dot(outward_normal) < 0.0 {
+                shading_normal = -shading_normal;
+            }
+            rec.normal = if rec.front_face {
+                shading_normal
+            } else {
+                -shading_normal
+            };
+        }
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colour::Colour;
+    use crate::material::Lambertian;
+    use crate::obj::parse_obj;
+
+    #[test]
+    fn test_hit_interpolates_normals() {
+        let obj = "\
+v -1 -1 0
+v 1 -1 0
+v 1 1 0
+v -1 1 0
+vn 0 0 1
+vn 0 0 1
+vn 1 0 1
+vn -1 0 1
+f 1//1 2//2 3//3 4//4
+";
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(Colour::default()));
+        let mesh = Mesh::new(parse_obj(obj.as_bytes()).unwrap(), mat.clone());
+        let mut rec = HitRecord::new(mat);
+
+        let r = Ray::new(Point3::new(0.0, -0.5, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(mesh.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+        assert!(rec.t == 2.0);
+        assert!(rec.front_face);
+        assert!((rec.normal - Vec3::new(0.25, 0.0, 1.0).unit_vector()).near_zero());
+
+        let r = Ray::new(Point3::new(-0.5, 0.5, -2.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(mesh.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+        assert!(!rec.front_face);
+        assert!((rec.normal - Vec3::new(0.25, 0.0, -1.0).unit_vector()).near_zero());
+    }
+}
@@ -0,0 +1,92 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+/// Moves any hittable in a straight line, from where it is at time 0 to
+/// `displacement` away from there at time 1.
+pub struct Moving {
+    object: Arc<dyn Hittable>,
+    displacement: Vec3,
+    bbox: Aabb,
+}
+
+impl Moving {
+    pub fn new(object: Arc<dyn Hittable>, displacement: Vec3) -> Self {
+        let start = object.bounding_box();
+        let bbox = Aabb::enclosing(&start, &(start + displacement));
+        Self {
+            object,
+            displacement,
+            bbox,
+        }
+    }
+}
+
+impl Hittable for Moving {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        // Move the ray back by the offset at its time instead of moving the object.
+        let offset = r.time() * self.displacement;
+        let moved =
+            Ray::with_time(r.origin() - offset, r.direction(), r.time()).with_sample(r.sample());
+        if !self.object.hit(&moved, ray_t, rec) {
+            return false;
+        }
+
+        rec.p += offset;
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    // Lights are sampled where they are at time 0, so scenes never add moving ones.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        self.object.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: Point3, u: [f64; 2]) -> Vec3 {
+        self.object.random(origin, u)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colour::Colour;
+    use crate::material::{Lambertian, Material};
+    use crate::sphere::Sphere;
+    use crate::vec3::Point3;
+
+    #[test]
+    fn test_hits_follow_ray_time() {
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(Colour::default()));
+        let start = Point3::new(0.0, 0.0, 0.0);
+        let end = Point3::new(4.0, 0.0, 0.0);
+        let moving_sphere = Sphere::moving(start, end, 1.0, mat.clone());
+        let moved_sphere = Moving::new(Arc::new(Sphere::new(start, 1.0, mat.clone())), end - start);
+
+        let mut rec = HitRecord::new(mat);
+        for object in [&moving_sphere as &dyn Hittable, &moved_sphere] {
+            let bbox = object.bounding_box();
+            assert!(bbox.x.min <= -1.0 && bbox.x.max >= 5.0);
+
+            for (time, x) in [(0.0, 0.0), (0.5, 2.0), (1.0, 4.0)] {
+                let r = Ray::with_time(Point3::new(x, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), time);
+                assert!(object.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+                assert!((rec.p - Point3::new(x, 0.0, 1.0)).near_zero());
+
+                let r = Ray::with_time(
+                    Point3::new(x + 2.5, 0.0, 5.0),
+                    Vec3::new(0.0, 0.0, -1.0),
+                    time,
+                );
+                assert!(!object.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+            }
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use crate::mesh::{MeshData, MeshFace};
+use crate::vec3::{Point3, Vec3};
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjError::Io(err) => write!(f, "{err}"),
+            ObjError::Parse { line, message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl Error for ObjError {}
+
+impl From<io::Error> for ObjError {
+    fn from(err: io::Error) -> Self {
+        ObjError::Io(err)
+    }
+}
+
+pub fn load_obj(path: &Path) -> Result<MeshData, ObjError> {
+    parse_obj(BufReader::new(File::open(path)?))
+}
+
+/// Parses positions, normals, texture coordinates, faces and groups from an
+/// OBJ file, fan-triangulating any face with more than three vertices.
+/// Faces that appear before the first `g` or `o` statement belong to the
+/// group `"default"`.
+pub fn parse_obj(reader: impl BufRead) -> Result<MeshData, ObjError> {
+    let mut data = MeshData {
+        groups: vec![String::from("default")],
+        ..Default::default()
+    };
+    let mut group = 0;
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line_number = index + 1;
+        let parse_error = |message: String| ObjError::Parse {
+            line: line_number,
+            message,
+        };
+
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => {
+                let v = parse_floats(&args, 3, 4).map_err(parse_error)?;
+                data.positions.push(Point3::new(v[0], v[1], v[2]));
+            }
+            "vn" => {
+                let n = parse_floats(&args, 3, 3).map_err(parse_error)?;
+                data.normals.push(Vec3::new(n[0], n[1], n[2]));
+            }
+            "vt" => {
+                let coords = parse_floats(&args, 1, 3).map_err(parse_error)?;
+                data.texcoords
+                    .push((coords[0], coords.get(1).copied().unwrap_or(0.0)));
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(parse_error(format!(
+                        "a face needs at least 3 vertices, found {}",
+                        args.len()
+                    )));
+                }
+                let vertices = args
+                    .iter()
+                    .map(|vertex| parse_vertex(vertex, &data))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(parse_error)?;
+
+                for k in 1..vertices.len() - 1 {
+                    let corners = [vertices[0], vertices[k], vertices[k + 1]];
+                    data.faces.push(MeshFace {
+                        positions: corners.map(|c| c.0),
+                        texcoords: all_present(corners.map(|c| c.1)),
+                        normals: all_present(corners.map(|c| c.2)),
+                        group,
+                    });
+                }
+            }
+            "g" | "o" => {
+                let name = if args.is_empty() {
+                    String::from("default")
+                } else {
+                    args.join(" ")
+                };
+                group = match data.groups.iter().position(|g| *g == name) {
+                    Some(existing) => existing,
+                    None => {
+                        data.groups.push(name);
+                        data.groups.len() - 1
+                    }
+                };
+            }
+            // Smoothing groups, material libraries, lines, points and
+            // free-form geometry carry nothing the mesh uses.
+            _ => {}
+        }
+    }
+
+    Ok(data)
+}
+
+fn parse_floats(args: &[&str], min: usize, max: usize) -> Result<Vec<f64>, String> {
+    if args.len() < min || args.len() > max {
+        return Err(format!(
+            "expected {} values, found {}",
+            if min == max {
+                min.to_string()
+            } else {
+                format!("{min} to {max}")
+            },
+            args.len()
+        ));
+    }
+    args.iter()
+        .map(|arg| {
+            arg.parse::<f64>()
+                .map_err(|_| format!("invalid number '{arg}'"))
+        })
+        .collect()
+}
+
+type Vertex = (usize, Option<usize>, Option<usize>);
+
+fn parse_vertex(vertex: &str, data: &MeshData) -> Result<Vertex, String> {
+    let mut parts = vertex.split('/');
+    let position = resolve_index(parts.next(), data.positions.len(), "position")?
+        .ok_or_else(|| format!("face vertex '{vertex}' has no position"))?;
+    let texcoord = resolve_index(parts.next(), data.texcoords.len(), "texture coordinate")?;
+    let normal = resolve_index(parts.next(), data.normals.len(), "normal")?;
+    Ok((position, texcoord, normal))
+}
+
+// OBJ indices start at 1, and negative indices count back from the latest element.
+fn resolve_index(index: Option<&str>, count: usize, kind: &str) -> Result<Option<usize>, String> {
+    let Some(index) = index.filter(|index| !index.is_empty()) else {
+        return Ok(None);
+    };
+    let value: i64 = index
+        .parse()
+        .map_err(|_| format!("invalid {kind} index '{index}'"))?;
+    let resolved = if value < 0 {
+        count as i64 + value
+    } else {
+        value - 1
+    };
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(format!("{kind} index {value} is out of range"));
+    }
+    Ok(Some(resolved as usize))
+}
+
+fn all_present(indices: [Option<usize>; 3]) -> Option<[usize; 3]> {
+    match indices {
+        [Some(a), Some(b), Some(c)] => Some([a, b, c]),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_triangulates_and_groups() {
+        let obj = "\
+# a unit square and a triangle
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vn 0 0 1
+vt 0 0
+g floor
+f 1//1 2//1 3//1 4//1
+o wall
+f -1 -2 -3
+";
+        let data = parse_obj(obj.as_bytes()).unwrap();
+
+        assert!(data.positions.len() == 4);
+        assert!(data.faces.len() == 3);
+        assert!(data.groups == vec!["default", "floor", "wall"]);
+        assert!(data.faces[1].positions == [0, 2, 3]);
+        assert!(data.faces[1].normals == Some([0, 0, 0]));
+        assert!(data.faces[1].texcoords.is_none());
+        assert!(data.faces[2].positions == [3, 2, 1]);
+        assert!(data.faces[2].group == 2);
+    }
+
+    #[test]
+    fn test_parse_errors_report_line() {
+        let obj = "v 0 0 0\nv 1 0 0\n\nf 1 2 5\n";
+        let err = parse_obj(obj.as_bytes()).unwrap_err();
+        assert!(err.to_string() == "line 4: position index 5 is out of range");
+
+        let err = parse_obj("v 0 zero 0\n".as_bytes()).unwrap_err();
+        assert!(err.to_string() == "line 1: invalid number 'zero'");
+    }
+
+    #[test]
+    fn test_parse_skips_unknown_statements() {
+        let obj = "\
+mtllib scene.mtl
+v 0 0 0
+v 1 0 0
+v 0 1 0
+vp 0.5 0.5
+cstype bspline
+bevel off
+usemtl red
+s 1
+f 1 2 3
+";
+        let data = parse_obj(obj.as_bytes()).unwrap();
+        assert!(data.positions.len() == 3);
+        assert!(data.faces.len() == 1);
+    }
+}
@@ -0,0 +1,277 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::aov::Aov;
+use crate::colour::Colour;
+use crate::exr::{self, Channel};
+use crate::framebuffer::{FrameBuffer, RenderLayers};
+use crate::tonemap::ToneMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    Png,
+    Ppm,
+    Hdr,
+    Pfm,
+    Exr,
+}
+
+impl ImageFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()?.to_str()?.parse().ok()
+    }
+
+    fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "png" => Some(ImageFormat::Png),
+            "ppm" => Some(ImageFormat::Ppm),
+            "hdr" => Some(ImageFormat::Hdr),
+            "pfm" => Some(ImageFormat::Pfm),
+            "exr" => Some(ImageFormat::Exr),
+            _ => None,
+        }
+    }
+
+    /// Whether the format stores linear, unclamped radiance rather than 8-bit display values.
+    pub fn is_hdr(&self) -> bool {
+        matches!(self, ImageFormat::Hdr | ImageFormat::Pfm | ImageFormat::Exr)
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ImageFormat::from_extension(s).ok_or_else(|| {
+            format!("unknown image format '{s}'; expected png, ppm, hdr, pfm or exr")
+        })
+    }
+}
+
+/// Writes `image` to `path` in the format implied by its extension, with the
+/// default tone mapping.
+pub fn write_image(path: &Path, image: &FrameBuffer) -> io::Result<()> {
+    let format = ImageFormat::from_path(path).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("unsupported image format: {}", path.display()),
+        )
+    })?;
+    write_image_as(path, image, format, &ToneMap::default())
+}
+
+/// Writes `image` to `path` as `format`. 8-bit formats pass through `tonemap`;
+/// HDR formats store the linear radiance untouched.
+pub fn write_image_as(
+    path: &Path,
+    image: &FrameBuffer,
+    format: ImageFormat,
+    tonemap: &ToneMap,
+) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    match format {
+        ImageFormat::Png => write_png(&mut out, image, tonemap)?,
+        ImageFormat::Ppm => write_ppm(&mut out, image, tonemap)?,
+        ImageFormat::Hdr => write_hdr(&mut out, image)?,
+        ImageFormat::Pfm => write_pfm(&mut out, image)?,
+        ImageFormat::Exr => write_exr(&mut out, image)?,
+    }
+    out.flush()
+}
+
+/// Writes the `aov` pass `layer` to `path` as `format`. HDR formats store the
+/// raw values; 8-bit formats pass colour passes through `tonemap` and show
+/// the rest as [`Aov::visualise`] pictures them.
+pub fn write_aov_as(
+    path: &Path,
+    aov: Aov,
+    layer: &FrameBuffer,
+    format: ImageFormat,
+    tonemap: &ToneMap,
+) -> io::Result<()> {
+    match format.is_hdr() || aov.is_colour() {
+        true => write_image_as(path, layer, format, tonemap),
+        false => write_image_as(path, &aov.visualise(layer), format, &ToneMap::default()),
+    }
+}
+
+/// Writes `image` to `path` as an EXR holding the AOVs of `layers` as extra
+/// layers, as [`write_exr_layers`] lays them out.
+pub fn write_layered_exr(
+    path: &Path,
+    image: &FrameBuffer,
+    layers: &RenderLayers,
+) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_exr_layers(&mut out, image, layers)?;
+    out.flush()
+}
+
+fn rgb8_bytes(image: &FrameBuffer, tonemap: &ToneMap) -> Vec<u8> {
+    image
+        .pixels()
+        .flat_map(|pixel_colour| tonemap.to_rgb8(pixel_colour))
+        .collect()
+}
+
+pub fn write_png(out: &mut impl Write, image: &FrameBuffer, tonemap: &ToneMap) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(out, image.width() as u32, image.height() as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer
+        .write_image_data(&rgb8_bytes(image, tonemap))
+        .map_err(io::Error::other)?;
+    writer.finish().map_err(io::Error::other)
+}
+
+pub fn write_ppm(out: &mut impl Write, image: &FrameBuffer, tonemap: &ToneMap) -> io::Result<()> {
+    write!(out, "P6\n{} {}\n255\n", image.width(), image.height())?;
+    out.write_all(&rgb8_bytes(image, tonemap))
+}
+
+/// Writes a Radiance RGBE image with flat (uncompressed) scanlines.
+pub fn write_hdr(out: &mut impl Write, image: &FrameBuffer) -> io::Result<()> {
+    write!(
+        out,
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        image.height(),
+        image.width()
+    )?;
+    let bytes: Vec<u8> = image.pixels().flat_map(|pixel| to_rgbe(&pixel)).collect();
+    out.write_all(&bytes)
+}
+
+fn to_rgbe(pixel_colour: &Colour) -> [u8; 4] {
+    let v = pixel_colour.x().max(pixel_colour.y()).max(pixel_colour.z());
+    if v < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+
+    let exponent = v.log2().floor() as i32 + 1;
+    let scale = 256.0 / 2.0_f64.powi(exponent);
+    [
+        (pixel_colour.x().max(0.0) * scale) as u8,
+        (pixel_colour.y().max(0.0) * scale) as u8,
+        (pixel_colour.z().max(0.0) * scale) as u8,
+        (exponent + 128) as u8,
+    ]
+}
+
+/// Writes a little-endian colour Portable Float Map, which stores its rows bottom to top.
+pub fn write_pfm(out: &mut impl Write, image: &FrameBuffer) -> io::Result<()> {
+    write!(out, "PF\n{} {}\n-1.0\n", image.width(), image.height())?;
+    let mut bytes = Vec::with_capacity(image.width() * image.height() * 12);
+    for j in (0..image.height()).rev() {
+        for i in 0..image.width() {
+            let pixel = image.pixel(i, j);
+            for c in 0..3 {
+                bytes.extend_from_slice(&(pixel[c] as f32).to_le_bytes());
+            }
+        }
+    }
+    out.write_all(&bytes)
+}
+
+pub fn write_exr(out: &mut impl Write, image: &FrameBuffer) -> io::Result<()> {
+    let planes: Vec<Vec<f32>> = (0..3)
+        .map(|c| image.pixels().map(|pixel| pixel[c] as f32).collect())
+        .collect();
+    let channels = ["R", "G", "B"]
+        .iter()
+        .zip(&planes)
+        .map(|(name, data)| Channel { name, data })
+        .collect::<Vec<_>>();
+    exr::write_exr(out, image.width(), image.height(), &channels)
+}
+
+/// Writes `image` as R, G and B with each AOV of `layers` beside it, in
+/// channels such as `depth.Z` and `normal.X`.
+pub fn write_exr_layers(
+    out: &mut impl Write,
+    image: &FrameBuffer,
+    layers: &RenderLayers,
+) -> io::Result<()> {
+    let mut names = vec![String::from("R"), String::from("G"), String::from("B")];
+    let mut planes: Vec<Vec<f32>> = (0..3)
+        .map(|c| image.pixels().map(|pixel| pixel[c] as f32).collect())
+        .collect();
+    for (aov, layer) in layers.layers() {
+        for (c, channel) in aov.channels().iter().enumerate() {
+            names.push(format!("{}.{channel}", aov.name()));
+            planes.push(layer.pixels().map(|pixel| pixel[c] as f32).collect());
+        }
+    }
+    let channels = names
+        .iter()
+        .zip(&planes)
+        .map(|(name, data)| Channel { name, data })
+        .collect::<Vec<_>>();
+    exr::write_exr(out, image.width(), image.height(), &channels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colour::Colour;
+
+    #[test]
+    fn test_write_ppm() {
+        let mut image = FrameBuffer::new(2, 1);
+        image.add_sample(0, 0, Colour::new(1.0, 0.0, 0.25), 1.0);
+        image.add_sample(1, 0, Colour::new(2.0, 2.0, 2.0), 4.0);
+
+        let mut out = Vec::new();
+        write_ppm(&mut out, &image, &ToneMap::default()).unwrap();
+        assert!(out == b"P6\n2 1\n255\n\xff\x00\x89\xbc\xbc\xbc");
+    }
+
+    #[test]
+    fn test_write_pfm() {
+        let mut image = FrameBuffer::new(1, 2);
+        image.add_sample(0, 0, Colour::new(1.0, 0.5, 0.25), 1.0);
+        image.add_sample(0, 1, Colour::new(2.0, 0.0, -1.0), 1.0);
+
+        let mut out = Vec::new();
+        write_pfm(&mut out, &image).unwrap();
+        let header = b"PF\n1 2\n-1.0\n";
+        assert!(out[..header.len()] == *header);
+        // Rows are stored bottom to top, as little-endian floats.
+        let values: Vec<f32> = out[header.len()..]
+            .chunks(4)
+            .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+            .collect();
+        assert!(values == [2.0, 0.0, -1.0, 1.0, 0.5, 0.25]);
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert!(ImageFormat::from_path(Path::new("out.PNG")) == Some(ImageFormat::Png));
+        assert!(ImageFormat::from_path(Path::new("out.ppm")) == Some(ImageFormat::Ppm));
+        assert!(ImageFormat::from_path(Path::new("out.exr")) == Some(ImageFormat::Exr));
+        assert!(ImageFormat::from_path(Path::new("out")).is_none());
+    }
+
+    #[test]
+    fn test_write_exr_layers() {
+        let layers = RenderLayers::new(2, 2, &[Aov::Depth, Aov::Normal]);
+        let mut out = Vec::new();
+        write_exr_layers(&mut out, &layers.colour, &layers).unwrap();
+
+        let header = String::from_utf8_lossy(&out);
+        for name in ["B\0", "depth.Z\0", "normal.X\0", "normal.Z\0"] {
+            assert!(header.contains(name));
+        }
+        assert!(!header.contains("depth.R"));
+    }
+
+    #[test]
+    fn test_to_rgbe() {
+        assert!(to_rgbe(&Colour::new(0.0, 0.0, 0.0)) == [0, 0, 0, 0]);
+        assert!(to_rgbe(&Colour::new(1.0, 0.5, 0.25)) == [128, 64, 32, 129]);
+        assert!(to_rgbe(&Colour::new(12.0, 0.0, 3.0)) == [192, 0, 48, 132]);
+    }
+}
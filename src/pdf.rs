@@ -0,0 +1,133 @@
+use crate::hittable::Hittable;
+use crate::rtweekend::PI;
+use crate::sampler;
+use crate::vec3::{Onb, Point3, Vec3};
+
+/// A probability density over directions, measured in solid angle.
+pub trait Pdf {
+    fn value(&self, direction: Vec3) -> f64;
+
+    /// A direction drawn from the density, warped from the sample point `u`.
+    fn generate(&self, u: [f64; 2]) -> Vec3;
+}
+
+/// Uniform over the whole sphere of directions.
+pub struct SpherePdf;
+
+impl Pdf for SpherePdf {
+    fn value(&self, _direction: Vec3) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn generate(&self, u: [f64; 2]) -> Vec3 {
+        sampler::sample_uniform_sphere(u)
+    }
+}
+
+/// Cosine-weighted over the hemisphere about a normal.
+pub struct CosinePdf {
+    uvw: Onb,
+}
+
+impl CosinePdf {
+    pub fn new(w: Vec3) -> Self {
+        Self { uvw: Onb::new(w) }
+    }
+}
+
+impl Pdf for CosinePdf {
+    fn value(&self, direction: Vec3) -> f64 {
+        let cosine_theta = direction.unit_vector().dot(self.uvw.w());
+        (cosine_theta / PI).max(0.0)
+    }
+
+    fn generate(&self, u: [f64; 2]) -> Vec3 {
+        self.uvw.transform(sampler::sample_cosine_hemisphere(u))
+    }
+}
+
+/// Directions from `origin` towards an object, as sampled by its
+/// `Hittable::random`.
+pub struct HittablePdf<'a> {
+    objects: &'a dyn Hittable,
+    origin: Point3,
+}
+
+impl<'a> HittablePdf<'a> {
+    pub fn new(objects: &'a dyn Hittable, origin: Point3) -> Self {
+        Self { objects, origin }
+    }
+}
+
+impl Pdf for HittablePdf<'_> {
+    fn value(&self, direction: Vec3) -> f64 {
+        self.objects.pdf_value(self.origin, direction)
+    }
+
+    fn generate(&self, u: [f64; 2]) -> Vec3 {
+        self.objects.random(self.origin, u)
+    }
+}
+
+/// Draws from one of two densities, picking the first with probability `weight`.
+pub struct MixturePdf<'a> {
+    p: [&'a dyn Pdf; 2],
+    weight: f64,
+}
+
+impl<'a> MixturePdf<'a> {
+    pub fn new(p0: &'a dyn Pdf, p1: &'a dyn Pdf, weight: f64) -> Self {
+        Self {
+            p: [p0, p1],
+            weight,
+        }
+    }
+}
+
+impl Pdf for MixturePdf<'_> {
+    fn value(&self, direction: Vec3) -> f64 {
+        self.weight * self.p[0].value(direction) + (1.0 - self.weight) * self.p[1].value(direction)
+    }
+
+    fn generate(&self, u: [f64; 2]) -> Vec3 {
+        if u[0] < self.weight {
+            self.p[0].generate([u[0] / self.weight, u[1]])
+        } else {
+            self.p[1].generate([(u[0] - self.weight) / (1.0 - self.weight), u[1]])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtweekend;
+
+    // The mean of 1 / pdf over samples drawn from a density estimates the solid
+    // angle it covers, which only holds if `generate` and `value` agree.
+    fn covered_solid_angle(pdf: &dyn Pdf) -> f64 {
+        let mut rng = rtweekend::seeded_rng(5);
+        let n = 100_000;
+        (0..n)
+            .map(|_| {
+                let u = [
+                    rtweekend::random_float(&mut rng),
+                    rtweekend::random_float(&mut rng),
+                ];
+                1.0 / pdf.value(pdf.generate(u))
+            })
+            .sum::<f64>()
+            / n as f64
+    }
+
+    #[test]
+    fn test_generate_matches_value() {
+        let sphere = SpherePdf;
+        let cosine = CosinePdf::new(Vec3::new(1.0, 2.0, -1.0));
+        assert!((covered_solid_angle(&sphere) - 4.0 * PI).abs() < 1e-9);
+        assert!((covered_solid_angle(&cosine) - 2.0 * PI).abs() < 0.05);
+        assert!(
+            (covered_solid_angle(&MixturePdf::new(&sphere, &cosine, 0.3)) - 4.0 * PI).abs() < 0.1
+        );
+    }
+}
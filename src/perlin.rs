@@ -0,0 +1,166 @@
+use crate::rtweekend::{self, Rng};
+use crate::vec3::{Point3, Vec3};
+
+const POINT_COUNT: usize = 256;
+
+/// Perlin gradient noise over a lattice of random unit vectors, hashed
+/// through three permutation tables.
+pub struct Perlin {
+    randvec: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    pub fn new(rng: &mut Rng) -> Self {
+        let randvec = (0..POINT_COUNT)
+            .map(|_| Vec3::random_unit_vector(rng))
+            .collect();
+        Self {
+            randvec,
+            perm_x: Perlin::generate_perm(rng),
+            perm_y: Perlin::generate_perm(rng),
+            perm_z: Perlin::generate_perm(rng),
+        }
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Perlin::new(&mut rtweekend::seeded_rng(seed))
+    }
+
+    /// Gradient noise in roughly `[-1, 1]`, zero at every lattice point.
+    pub fn noise(&self, p: &Point3) -> f64 {
+        let (i, u) = split(p.x());
+        let (j, v) = split(p.y());
+        let (k, w) = split(p.z());
+
+        // Hermite smoothing of the interpolation weights hides the lattice.
+        let uu = u * u * (3.0 - 2.0 * u);
+        let vv = v * v * (3.0 - 2.0 * v);
+        let ww = w * w * (3.0 - 2.0 * w);
+
+        let mut accum = 0.0;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let gradient = self.randvec[self.hash(i + di, j + dj, k + dk)];
+                    let (fi, fj, fk) = (di as f64, dj as f64, dk as f64);
+                    let weight = Vec3::new(u - fi, v - fj, w - fk);
+                    accum += (fi * uu + (1.0 - fi) * (1.0 - uu))
+                        * (fj * vv + (1.0 - fj) * (1.0 - vv))
+                        * (fk * ww + (1.0 - fk) * (1.0 - ww))
+                        * gradient.dot(weight);
+                }
+            }
+        }
+        accum
+    }
+
+    /// Sum of `depth` octaves of absolute noise, each at double the frequency
+    /// and half the amplitude of the last.
+    pub fn turb(&self, p: &Point3, depth: u32) -> f64 {
+        let mut accum = 0.0;
+        let mut temp_p = *p;
+        let mut weight = 1.0;
+        for _ in 0..depth {
+            accum += weight * self.noise(&temp_p);
+            weight *= 0.5;
+            temp_p = 2.0 * temp_p;
+        }
+        accum.abs()
+    }
+
+    /// Fractional Brownian motion: signed octaves of noise with the given
+    /// frequency and amplitude multipliers, normalised to roughly `[-1, 1]`.
+    pub fn fbm(&self, p: &Point3, octaves: u32, lacunarity: f64, gain: f64) -> f64 {
+        let mut accum = 0.0;
+        let mut total_weight = 0.0;
+        let mut temp_p = *p;
+        let mut weight = 1.0;
+        for _ in 0..octaves {
+            accum += weight * self.noise(&temp_p);
+            total_weight += weight;
+            weight *= gain;
+            temp_p = lacunarity * temp_p;
+        }
+        if total_weight > 0.0 {
+            accum / total_weight
+        } else {
+            0.0
+        }
+    }
+
+    /// Distance from `p` to the nearest of a set of feature points, one placed
+    /// at random in each lattice cell (Worley's cellular noise).
+    pub fn cellular(&self, p: &Point3) -> f64 {
+        let (i, _) = split(p.x());
+        let (j, _) = split(p.y());
+        let (k, _) = split(p.z());
+
+        let mut nearest = f64::INFINITY;
+        for di in -1..=1 {
+            for dj in -1..=1 {
+                for dk in -1..=1 {
+                    let cell = Point3::new((i + di) as f64, (j + dj) as f64, (k + dk) as f64);
+                    // Half of a unit vector keeps each feature point inside its own cell.
+                    let offset = 0.5 * self.randvec[self.hash(i + di, j + dj, k + dk)];
+                    let feature = cell + Vec3::new(0.5, 0.5, 0.5) + offset;
+                    nearest = nearest.min((feature - *p).length_squared());
+                }
+            }
+        }
+        nearest.sqrt()
+    }
+
+    fn hash(&self, i: i64, j: i64, k: i64) -> usize {
+        let mask = POINT_COUNT as i64 - 1;
+        self.perm_x[(i & mask) as usize]
+            ^ self.perm_y[(j & mask) as usize]
+            ^ self.perm_z[(k & mask) as usize]
+    }
+
+    fn generate_perm(rng: &mut Rng) -> Vec<usize> {
+        let mut p: Vec<usize> = (0..POINT_COUNT).collect();
+        for i in (1..POINT_COUNT).rev() {
+            let target = rtweekend::random_int(0, i as i64, rng) as usize;
+            p.swap(i, target);
+        }
+        p
+    }
+}
+
+// Splits a coordinate into its lattice cell and the offset within it.
+fn split(x: f64) -> (i64, f64) {
+    let floor = x.floor();
+    (floor as i64, x - floor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_noise_is_seeded() {
+        let a = Perlin::with_seed(7);
+        let b = Perlin::with_seed(7);
+        let c = Perlin::with_seed(8);
+        let p = Point3::new(1.3, -2.7, 0.4);
+        assert!(a.noise(&p) == b.noise(&p));
+        assert!(a.noise(&p) != c.noise(&p));
+        assert!(a.noise(&Point3::new(3.0, -1.0, 5.0)) == 0.0);
+    }
+
+    #[test]
+    fn test_noise_range() {
+        let perlin = Perlin::with_seed(1);
+        let mut rng = rtweekend::seeded_rng(2);
+        for _ in 0..1000 {
+            let p = Vec3::random_range(-10.0, 10.0, &mut rng);
+            assert!(perlin.noise(&p).abs() <= 1.0);
+            assert!((-1.0..=1.0).contains(&perlin.fbm(&p, 5, 2.0, 0.5)));
+            // The feature point of the cell containing `p` is always within reach.
+            assert!(perlin.cellular(&p) <= 3.0f64.sqrt());
+        }
+    }
+}
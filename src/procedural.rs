@@ -0,0 +1,133 @@
+use crate::colour::Colour;
+use crate::interval::Interval;
+use crate::perlin::Perlin;
+use crate::texture::Texture;
+use crate::vec3::Point3;
+
+/// How a [`NoiseTexture`] turns Perlin noise into a blend factor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pattern {
+    /// Plain gradient noise.
+    Noise,
+    /// `depth` octaves of absolute noise.
+    Turbulence { depth: u32 },
+    /// Sine bands along z, phase-shifted by turbulence.
+    Marble { depth: u32 },
+    /// Concentric rings around the y axis, distorted by turbulence.
+    Wood { depth: u32 },
+    /// Distance to the nearest cell feature point.
+    Voronoi,
+    /// Fractional Brownian motion.
+    Fbm {
+        octaves: u32,
+        lacunarity: f64,
+        gain: f64,
+    },
+}
+
+/// A procedural texture blending between two colours by a noise pattern.
+/// The same seed always gives the same texture.
+pub struct NoiseTexture {
+    noise: Perlin,
+    pattern: Pattern,
+    scale: f64,
+    low: Colour,
+    high: Colour,
+}
+
+impl NoiseTexture {
+    pub fn new(pattern: Pattern, scale: f64, seed: u64) -> Self {
+        NoiseTexture::with_colours(
+            pattern,
+            scale,
+            seed,
+            Colour::new(0.0, 0.0, 0.0),
+            Colour::new(1.0, 1.0, 1.0),
+        )
+    }
+
+    pub fn with_colours(
+        pattern: Pattern,
+        scale: f64,
+        seed: u64,
+        low: Colour,
+        high: Colour,
+    ) -> Self {
+        Self {
+            noise: Perlin::with_seed(seed),
+            pattern,
+            scale,
+            low,
+            high,
+        }
+    }
+
+    fn factor(&self, p: &Point3) -> f64 {
+        let sp = self.scale * *p;
+        match self.pattern {
+            Pattern::Noise => 0.5 * (1.0 + self.noise.noise(&sp)),
+            Pattern::Turbulence { depth } => self.noise.turb(&sp, depth),
+            Pattern::Marble { depth } => {
+                0.5 * (1.0 + (sp.z() + 10.0 * self.noise.turb(p, depth)).sin())
+            }
+            Pattern::Wood { depth } => {
+                let rings =
+                    (sp.x() * sp.x() + sp.z() * sp.z()).sqrt() + 2.0 * self.noise.turb(&sp, depth);
+                rings - rings.floor()
+            }
+            Pattern::Voronoi => self.noise.cellular(&sp),
+            Pattern::Fbm {
+                octaves,
+                lacunarity,
+                gain,
+            } => 0.5 * (1.0 + self.noise.fbm(&sp, octaves, lacunarity, gain)),
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Colour {
+        let t = Interval::new(0.0, 1.0).clamp(self.factor(p));
+        (1.0 - t) * self.low + t * self.high
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtweekend;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn test_patterns_blend_between_colours() {
+        let low = Colour::new(0.2, 0.1, 0.0);
+        let high = Colour::new(0.8, 0.6, 0.4);
+        let patterns = [
+            Pattern::Noise,
+            Pattern::Turbulence { depth: 7 },
+            Pattern::Marble { depth: 7 },
+            Pattern::Wood { depth: 3 },
+            Pattern::Voronoi,
+            Pattern::Fbm {
+                octaves: 5,
+                lacunarity: 2.0,
+                gain: 0.5,
+            },
+        ];
+        let mut rng = rtweekend::seeded_rng(3);
+        for pattern in patterns {
+            let a = NoiseTexture::with_colours(pattern, 4.0, 11, low, high);
+            let b = NoiseTexture::with_colours(pattern, 4.0, 11, low, high);
+            for _ in 0..100 {
+                let p = Vec3::random_range(-5.0, 5.0, &mut rng);
+                let colour = a.value(0.0, 0.0, &p);
+                assert!(colour == b.value(0.0, 0.0, &p));
+                for axis in 0..3 {
+                    assert!(
+                        colour[axis] >= low[axis] - 1e-12 && colour[axis] <= high[axis] + 1e-12
+                    );
+                }
+            }
+        }
+    }
+}
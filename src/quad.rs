@@ -0,0 +1,164 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::{self, HitRecord, Hittable};
+use crate::hittable_list::HittableList;
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+/// A parallelogram with corner `q` and edges `u` and `v`.
+pub struct Quad {
+    q: Point3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+    normal: Vec3,
+    d: f64,
+    area: f64,
+}
+
+impl Quad {
+    pub fn new(q: Point3, u: Vec3, v: Vec3, mat: Arc<dyn Material>) -> Self {
+        let n = u.cross(v);
+        let normal = n.unit_vector();
+        let d = normal.dot(q);
+        let w = n / n.dot(n);
+
+        let bbox_diagonal1 = Aabb::from_points(q, q + u + v);
+        let bbox_diagonal2 = Aabb::from_points(q + u, q + v);
+
+        Self {
+            q,
+            u,
+            v,
+            w,
+            mat,
+            bbox: Aabb::enclosing(&bbox_diagonal1, &bbox_diagonal2),
+            normal,
+            d,
+            area: n.length(),
+        }
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let denom = self.normal.dot(r.direction());
+
+        // No hit if the ray is parallel to the plane.
+        if denom.abs() < 1e-8 {
+            return false;
+        }
+
+        let t = (self.d - self.normal.dot(r.origin())) / denom;
+        if !ray_t.contains(t) {
+            return false;
+        }
+
+        // Express the intersection in the plane's (u, v) coordinates to test it against the edges.
+        let intersection = r.at(t);
+        let planar_hitpt_vector = intersection - self.q;
+        let alpha = self.w.dot(planar_hitpt_vector.cross(self.v));
+        let beta = self.w.dot(self.u.cross(planar_hitpt_vector));
+
+        let unit_interval = Interval::new(0.0, 1.0);
+        if !unit_interval.contains(alpha) || !unit_interval.contains(beta) {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = intersection;
+        rec.u = alpha;
+        rec.v = beta;
+        rec.mat = self.mat.clone();
+        rec.set_face_normal(r, &self.normal);
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        hittable::area_pdf_value(self, self.area, origin, direction)
+    }
+
+    fn random(&self, origin: Point3, u: [f64; 2]) -> Vec3 {
+        let p = self.q + (u[0] * self.u) + (u[1] * self.v);
+        p - origin
+    }
+}
+
+/// The six sides of the axis-aligned box with opposite corners `a` and `b`.
+pub fn make_box(a: Point3, b: Point3, mat: Arc<dyn Material>) -> HittableList {
+    let mut sides = HittableList::default();
+
+    let min = Point3::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z()));
+    let max = Point3::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z()));
+
+    let dx = Vec3::new(max.x() - min.x(), 0.0, 0.0);
+    let dy = Vec3::new(0.0, max.y() - min.y(), 0.0);
+    let dz = Vec3::new(0.0, 0.0, max.z() - min.z());
+
+    let faces = [
+        (Point3::new(min.x(), min.y(), max.z()), dx, dy), // front
+        (Point3::new(max.x(), min.y(), max.z()), -dz, dy), // right
+        (Point3::new(max.x(), min.y(), min.z()), -dx, dy), // back
+        (Point3::new(min.x(), min.y(), min.z()), dz, dy), // left
+        (Point3::new(min.x(), max.y(), max.z()), dx, -dz), // top
+        (Point3::new(min.x(), min.y(), min.z()), dx, dz), // bottom
+    ];
+    for (q, u, v) in faces {
+        sides.add(Arc::new(Quad::new(q, u, v, mat.clone())));
+    }
+
+    sides
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colour::Colour;
+    use crate::material::Lambertian;
+
+    #[test]
+    fn test_hit() {
+        let quad = Quad::new(
+            Point3::new(-1.0, -1.0, -2.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Arc::new(Lambertian::new(Colour::default())),
+        );
+        let inside = Ray::new(Point3::new(0.5, -0.5, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let outside = Ray::new(Point3::new(0.5, 0.5, 0.0), Vec3::new(0.0, 0.0, -1.0));
+
+        let mut rec = HitRecord::default();
+        assert!(quad.hit(&inside, Interval::new(0.001, f64::INFINITY), &mut rec));
+        assert!(rec.t == 2.0);
+        assert!(rec.front_face);
+        assert!(rec.normal == Vec3::new(0.0, 0.0, 1.0));
+        assert!(!quad.hit(&outside, Interval::new(0.001, f64::INFINITY), &mut rec));
+    }
+
+    #[test]
+    fn test_make_box_bounds() {
+        let sides = make_box(
+            Point3::new(1.0, 2.0, 3.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Arc::new(Lambertian::new(Colour::default())),
+        );
+        assert!(sides.len() == 6);
+
+        // Each face is padded slightly, so the bounds only approximately match the corners.
+        let bbox = sides.bounding_box();
+        assert!((bbox.x.size() - 1.0).abs() < 1e-3);
+        assert!((bbox.y.size() - 2.0).abs() < 1e-3);
+        assert!((bbox.z.size() - 3.0).abs() < 1e-3);
+        assert!(bbox.x.min.abs() < 1e-3);
+    }
+}
@@ -1,14 +1,30 @@
 use crate::vec3::{Point3, Vec3};
 
-#[derive(Default)]
+#[derive(Debug, Default, Clone, Copy)]
 pub struct Ray {
     orig: Point3,
     dir: Vec3,
+    tm: f64,
+    sample: f64,
 }
 
 impl Ray {
     pub fn new(orig: Point3, dir: Vec3) -> Self {
-        Self { orig, dir }
+        Ray::with_time(orig, dir, 0.0)
+    }
+
+    pub fn with_time(orig: Point3, dir: Vec3, tm: f64) -> Self {
+        Self {
+            orig,
+            dir,
+            tm,
+            sample: 0.5,
+        }
+    }
+
+    /// The same ray carrying `sample` for choosing where it scatters in a volume.
+    pub fn with_sample(self, sample: f64) -> Self {
+        Self { sample, ..self }
     }
 
     pub fn origin(&self) -> Point3 {
@@ -19,6 +35,18 @@ impl Ray {
         self.dir
     }
 
+    /// The moment within the camera shutter at which the ray was cast.
+    pub fn time(&self) -> f64 {
+        self.tm
+    }
+
+    /// A uniform sample in `[0, 1)` from the camera sample that cast the ray,
+    /// used to pick where it scatters inside participating media. Each medium
+    /// hashes it with where the ray crosses its boundary.
+    pub fn sample(&self) -> f64 {
+        self.sample
+    }
+
     pub fn at(&self, t: f64) -> Point3 {
         self.orig + t * self.dir
     }
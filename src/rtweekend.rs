@@ -1,16 +1,43 @@
-use rand::Rng;
+use rand::{Rng as _, SeedableRng};
+use rand_pcg::Pcg32;
 
 pub const INFINITY: f64 = f64::INFINITY;
 pub const PI: f64 = std::f64::consts::PI;
 
+/// The random number generator threaded through every sampling routine.
+pub type Rng = Pcg32;
+
 pub fn degrees_to_radians(degrees: f64) -> f64 {
     degrees * PI / 180.0
 }
 
-pub fn random_float() -> f64 {
-    random_float_range(0.0, 1.0)
+pub fn seeded_rng(seed: u64) -> Rng {
+    Pcg32::seed_from_u64(seed)
+}
+
+/// An RNG for a single camera sample, derived only from the global seed, the
+/// pixel and the sample index so that renders do not depend on thread scheduling.
+pub fn sample_rng(seed: u64, pixel_index: u64, sample_index: u64) -> Rng {
+    let state = splitmix64(seed ^ splitmix64(pixel_index ^ splitmix64(sample_index)));
+    Pcg32::new(state, pixel_index)
+}
+
+pub(crate) fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+pub fn random_float(rng: &mut Rng) -> f64 {
+    random_float_range(0.0, 1.0, rng)
+}
+
+pub fn random_float_range(range_min: f64, range_max: f64, rng: &mut Rng) -> f64 {
+    rng.gen_range(range_min..range_max)
 }
 
-pub fn random_float_range(range_min: f64, range_max: f64) -> f64 {
-    rand::thread_rng().gen_range(range_min..range_max)
+/// Returns a random integer in `[min, max]`.
+pub fn random_int(min: i64, max: i64, rng: &mut Rng) -> i64 {
+    rng.gen_range(min..=max)
 }
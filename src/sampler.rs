@@ -0,0 +1,615 @@
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use crate::rtweekend::{self, splitmix64, Rng, PI};
+use crate::vec3::Vec3;
+
+/// A source of sample points for the camera, drawn one dimension at a time.
+/// Each camera sample asks for its dimensions in the same order: pixel
+/// position, lens position, time and where the ray scatters in volumes, then
+/// a fixed block for every bounce (the scattering and light directions, where
+/// the shadow and scattered rays scatter in volumes, and the Russian roulette
+/// draw), so a sampler can spread each dimension evenly over a pixel's samples.
+pub trait Sampler {
+    /// Starts sample `index` of pixel `(i, j)`, rewinding to the first dimension.
+    fn start_pixel_sample(&mut self, i: i64, j: i64, index: u32);
+
+    /// The next dimension, in `[0, 1)`.
+    fn get_1d(&mut self) -> f64;
+
+    /// The next two dimensions, in `[0, 1)²`.
+    fn get_2d(&mut self) -> [f64; 2];
+}
+
+/// Which [`Sampler`] the camera draws its samples from.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum SamplerKind {
+    /// Uniform random numbers with no correlation between samples.
+    #[default]
+    Independent,
+    /// One jittered sample per stratum, shuffled independently per dimension.
+    Stratified,
+    /// The Halton sequence, randomly shifted per pixel.
+    Halton,
+    /// Owen-scrambled Sobol points, shuffled per dimension pair.
+    Sobol,
+    /// Scrambled Sobol points shared by every pixel and shifted by a blue-noise
+    /// mask, so what error remains is spread evenly across the image.
+    BlueNoise,
+}
+
+impl SamplerKind {
+    /// A sampler of this kind for pixels taking `samples_per_pixel` samples.
+    pub fn build(&self, seed: u64, samples_per_pixel: u32) -> Box<dyn Sampler> {
+        let state = SampleState::new(seed);
+        match self {
+            SamplerKind::Independent => Box::new(IndependentSampler {
+                state,
+                rng: rtweekend::seeded_rng(seed),
+            }),
+            SamplerKind::Stratified => Box::new(StratifiedSampler {
+                state,
+                samples_per_pixel: samples_per_pixel.max(1),
+            }),
+            SamplerKind::Halton => Box::new(HaltonSampler { state }),
+            SamplerKind::Sobol => Box::new(SobolSampler { state }),
+            SamplerKind::BlueNoise => Box::new(BlueNoiseSampler { state }),
+        }
+    }
+}
+
+impl FromStr for SamplerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "independent" => Ok(SamplerKind::Independent),
+            "stratified" => Ok(SamplerKind::Stratified),
+            "halton" => Ok(SamplerKind::Halton),
+            "sobol" => Ok(SamplerKind::Sobol),
+            "blue-noise" | "blue_noise" => Ok(SamplerKind::BlueNoise),
+            _ => Err(format!(
+                "unknown sampler '{s}'; expected independent, stratified, halton, sobol or blue-noise"
+            )),
+        }
+    }
+}
+
+/// The pixel, sample and dimension every sampler keeps track of.
+struct SampleState {
+    seed: u64,
+    i: i64,
+    j: i64,
+    pixel: u64,
+    index: u32,
+    dimension: u64,
+}
+
+impl SampleState {
+    fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            i: 0,
+            j: 0,
+            pixel: 0,
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn start(&mut self, i: i64, j: i64, index: u32) {
+        self.i = i;
+        self.j = j;
+        self.pixel = hash(&[i as u64, j as u64]);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    /// A hash of the seed, the pixel and the next dimension, which it then skips past.
+    fn next_dimension(&mut self, count: u64) -> u64 {
+        let h = hash(&[self.seed, self.pixel, self.dimension]);
+        self.dimension += count;
+        h
+    }
+}
+
+struct IndependentSampler {
+    state: SampleState,
+    rng: Rng,
+}
+
+impl Sampler for IndependentSampler {
+    fn start_pixel_sample(&mut self, i: i64, j: i64, index: u32) {
+        self.state.start(i, j, index);
+        self.rng = rtweekend::sample_rng(
+            hash(&[self.state.seed, INDEPENDENT_STREAM]),
+            self.state.pixel,
+            index as u64,
+        );
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        rtweekend::random_float(&mut self.rng)
+    }
+
+    fn get_2d(&mut self) -> [f64; 2] {
+        [self.get_1d(), self.get_1d()]
+    }
+}
+
+/// Keeps the independent sampler's stream apart from other random streams
+/// derived from the same render seed.
+const INDEPENDENT_STREAM: u64 = 0x5a4d_9e1b_3c27_f680;
+
+struct StratifiedSampler {
+    state: SampleState,
+    samples_per_pixel: u32,
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_pixel_sample(&mut self, i: i64, j: i64, index: u32) {
+        self.state.start(i, j, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let h = self.state.next_dimension(1);
+        let n = self.samples_per_pixel;
+        let stratum = permutation_element(self.state.index % n, n, h as u32);
+        let jitter = unit_from_u64(hash(&[h, self.state.index as u64]));
+        (stratum as f64 + jitter) / n as f64
+    }
+
+    fn get_2d(&mut self) -> [f64; 2] {
+        let h = self.state.next_dimension(2);
+        let n = self.samples_per_pixel;
+        let nx = (n as f64).sqrt().ceil() as u32;
+        let ny = n.div_ceil(nx);
+        let cell = permutation_element(self.state.index % (nx * ny), nx * ny, h as u32);
+        let jitter = hash(&[h, self.state.index as u64]);
+        [
+            ((cell % nx) as f64 + unit_from_u64(jitter)) / nx as f64,
+            ((cell / nx) as f64 + unit_from_u64(splitmix64(jitter))) / ny as f64,
+        ]
+    }
+}
+
+struct HaltonSampler {
+    state: SampleState,
+}
+
+impl HaltonSampler {
+    fn sample(&mut self) -> f64 {
+        let dimension = self.state.dimension as usize;
+        let shift = unit_from_u64(self.state.next_dimension(1));
+        match PRIMES.get(dimension) {
+            Some(&base) => (radical_inverse(base, self.state.index as u64) + shift).fract(),
+            None => unit_from_u64(hash(&[shift.to_bits(), self.state.index as u64])),
+        }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_pixel_sample(&mut self, i: i64, j: i64, index: u32) {
+        self.state.start(i, j, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        self.sample()
+    }
+
+    fn get_2d(&mut self) -> [f64; 2] {
+        [self.sample(), self.sample()]
+    }
+}
+
+/// Bases for the Halton sequence's dimensions; later dimensions are random.
+const PRIMES: [u32; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131,
+];
+
+struct SobolSampler {
+    state: SampleState,
+}
+
+impl Sampler for SobolSampler {
+    fn start_pixel_sample(&mut self, i: i64, j: i64, index: u32) {
+        self.state.start(i, j, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        owen_sobol_1d(self.state.index, self.state.next_dimension(1))
+    }
+
+    fn get_2d(&mut self) -> [f64; 2] {
+        owen_sobol_2d(self.state.index, self.state.next_dimension(2))
+    }
+}
+
+struct BlueNoiseSampler {
+    state: SampleState,
+}
+
+impl BlueNoiseSampler {
+    /// A hash of the seed and the next dimension alone, so every pixel sees
+    /// the same points, together with this pixel's shift for it.
+    fn next_dimension(&mut self) -> (u64, f64) {
+        let h = hash(&[self.state.seed, self.state.dimension]);
+        self.state.dimension += 1;
+        let mask = blue_noise_mask();
+        let x = (self.state.i + (h >> 32) as i64).rem_euclid(MASK_SIZE as i64) as usize;
+        let y = (self.state.j + (h & 0xffff_ffff) as i64).rem_euclid(MASK_SIZE as i64) as usize;
+        (h, mask[y * MASK_SIZE + x])
+    }
+}
+
+impl Sampler for BlueNoiseSampler {
+    fn start_pixel_sample(&mut self, i: i64, j: i64, index: u32) {
+        self.state.start(i, j, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let (h, shift) = self.next_dimension();
+        (owen_sobol_1d(self.state.index, h) + shift).fract()
+    }
+
+    fn get_2d(&mut self) -> [f64; 2] {
+        let (h, shift_x) = self.next_dimension();
+        let (_, shift_y) = self.next_dimension();
+        let [x, y] = owen_sobol_2d(self.state.index, h);
+        [(x + shift_x).fract(), (y + shift_y).fract()]
+    }
+}
+
+/// A point on the unit disk, uniform by area.
+pub fn sample_uniform_disk(u: [f64; 2]) -> Vec3 {
+    // Shirley and Chiu's concentric mapping keeps strata in the square adjacent on the disk.
+    let (x, y) = (2.0 * u[0] - 1.0, 2.0 * u[1] - 1.0);
+    if x == 0.0 && y == 0.0 {
+        return Vec3::default();
+    }
+    let (r, theta) = if x.abs() > y.abs() {
+        (x, PI / 4.0 * (y / x))
+    } else {
+        (y, PI / 2.0 - PI / 4.0 * (x / y))
+    };
+    Vec3::new(r * theta.cos(), r * theta.sin(), 0.0)
+}
+
+/// A direction about the z axis, with density proportional to its z component.
+pub fn sample_cosine_hemisphere(u: [f64; 2]) -> Vec3 {
+    let d = sample_uniform_disk(u);
+    let z = (1.0 - d.x() * d.x() - d.y() * d.y()).max(0.0).sqrt();
+    Vec3::new(d.x(), d.y(), z)
+}
+
+/// A direction uniform over the whole sphere.
+pub fn sample_uniform_sphere(u: [f64; 2]) -> Vec3 {
+    let z = 1.0 - 2.0 * u[0];
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * u[1];
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// Picks one of `count` choices with `u`, returning it together with `u`
+/// stretched back over `[0, 1)` so the same number can drive a further choice.
+pub fn split_sample(u: f64, count: usize) -> (usize, f64) {
+    let scaled = u * count as f64;
+    let index = (scaled as usize).min(count - 1);
+    (index, (scaled - index as f64).min(ONE_MINUS_EPSILON))
+}
+
+/// A uniform sample in `[0, 1)` hashed from `keys`, uncorrelated with the
+/// samples of any other keys.
+pub fn hashed_sample(keys: &[u64]) -> f64 {
+    unit_from_u64(hash(keys))
+}
+
+const ONE_MINUS_EPSILON: f64 = 1.0 - f64::EPSILON / 2.0;
+
+fn hash(values: &[u64]) -> u64 {
+    values
+        .iter()
+        .fold(0x6a09_e667_f3bc_c908, |h, &v| splitmix64(h ^ splitmix64(v)))
+}
+
+fn unit_from_u64(bits: u64) -> f64 {
+    (bits >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+}
+
+fn unit_from_u32(bits: u32) -> f64 {
+    bits as f64 * (1.0 / (1u64 << 32) as f64)
+}
+
+/// Element `i` of a random permutation of `0..l` chosen by `p`, without
+/// building the permutation (Kensler, "Correlated Multi-Jittered Sampling").
+fn permutation_element(mut i: u32, l: u32, p: u32) -> u32 {
+    let mut w = l - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < l {
+            break;
+        }
+    }
+    i.wrapping_add(p) % l
+}
+
+/// The digits of `a` in `base`, mirrored about the radix point.
+fn radical_inverse(base: u32, mut a: u64) -> f64 {
+    let base = base as u64;
+    let inv_base = 1.0 / base as f64;
+    let mut reversed = 0u64;
+    let mut inv_base_n = 1.0;
+    while a > 0 {
+        let next = a / base;
+        reversed = reversed * base + (a - next * base);
+        inv_base_n *= inv_base;
+        a = next;
+    }
+    (reversed as f64 * inv_base_n).min(ONE_MINUS_EPSILON)
+}
+
+/// A random permutation of the bits of `x` that maps every aligned power-of-two
+/// block of integers onto another (Laine and Karras).
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x
+}
+
+/// Owen scrambling of the fixed-point fraction `x`.
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+/// The second dimension of the Sobol sequence; the first is `index.reverse_bits()`.
+fn sobol_second_dimension(mut index: u32) -> u32 {
+    let mut v = 1u32 << 31;
+    let mut result = 0;
+    while index != 0 {
+        if index & 1 != 0 {
+            result ^= v;
+        }
+        index >>= 1;
+        v ^= v >> 1;
+    }
+    result
+}
+
+// Burley, "Practical Hash-based Owen Scrambling": each dimension (or pair)
+// shuffles the sample order and scrambles the first Sobol dimensions, which
+// keeps them stratified without tables for higher dimensions.
+fn owen_sobol_1d(index: u32, h: u64) -> f64 {
+    let shuffled = nested_uniform_scramble(index, h as u32);
+    unit_from_u32(nested_uniform_scramble(
+        shuffled.reverse_bits(),
+        (h >> 32) as u32,
+    ))
+}
+
+fn owen_sobol_2d(index: u32, h: u64) -> [f64; 2] {
+    let shuffled = nested_uniform_scramble(index, h as u32);
+    let h2 = splitmix64(h);
+    [
+        unit_from_u32(nested_uniform_scramble(
+            shuffled.reverse_bits(),
+            (h >> 32) as u32,
+        )),
+        unit_from_u32(nested_uniform_scramble(
+            sobol_second_dimension(shuffled),
+            h2 as u32,
+        )),
+    ]
+}
+
+const MASK_SIZE: usize = 64;
+
+/// A tileable `MASK_SIZE`² blue-noise mask with values evenly spread over
+/// `[0, 1)`, built on first use.
+fn blue_noise_mask() -> &'static [f64] {
+    static MASK: OnceLock<Vec<f64>> = OnceLock::new();
+    MASK.get_or_init(|| void_and_cluster(MASK_SIZE))
+}
+
+/// Ulichney's void-and-cluster method: ranks every cell of a toroidal grid so
+/// that each prefix of the ranking is as evenly spread as it can be.
+fn void_and_cluster(size: usize) -> Vec<f64> {
+    let n = size * size;
+    let sigma = 1.5;
+    let kernel: Vec<f64> = (0..n)
+        .map(|k| {
+            let (dx, dy) = (k % size, k / size);
+            let (dx, dy) = (dx.min(size - dx) as f64, dy.min(size - dy) as f64);
+            (-(dx * dx + dy * dy) / (2.0 * sigma * sigma)).exp()
+        })
+        .collect();
+    let splat = |energy: &mut [f64], p: usize, sign: f64| {
+        let (px, py) = (p % size, p / size);
+        for (q, e) in energy.iter_mut().enumerate() {
+            let dx = (q % size + size - px) % size;
+            let dy = (q / size + size - py) % size;
+            *e += sign * kernel[dy * size + dx];
+        }
+    };
+    let tightest_cluster = |energy: &[f64], points: &[bool]| {
+        (0..n)
+            .filter(|&p| points[p])
+            .max_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+            .expect("pattern has no points")
+    };
+    let largest_void = |energy: &[f64], points: &[bool]| {
+        (0..n)
+            .filter(|&p| !points[p])
+            .min_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+            .expect("pattern is full")
+    };
+
+    // An initial random pattern, relaxed until moving its tightest point to
+    // the largest void would put it straight back, or after one swap per cell
+    // should it never settle.
+    let mut rng = rtweekend::seeded_rng(0);
+    let mut points = vec![false; n];
+    let mut energy = vec![0.0; n];
+    let initial = n / 10;
+    let mut placed = 0;
+    while placed < initial {
+        let p = rtweekend::random_int(0, n as i64 - 1, &mut rng) as usize;
+        if !points[p] {
+            points[p] = true;
+            splat(&mut energy, p, 1.0);
+            placed += 1;
+        }
+    }
+    for _ in 0..n {
+        let cluster = tightest_cluster(&energy, &points);
+        points[cluster] = false;
+        splat(&mut energy, cluster, -1.0);
+        let void = largest_void(&energy, &points);
+        points[void] = true;
+        splat(&mut energy, void, 1.0);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut rank = vec![0; n];
+    let (mut removed, mut removed_energy) = (points.clone(), energy.clone());
+    for r in (0..initial).rev() {
+        let cluster = tightest_cluster(&removed_energy, &removed);
+        removed[cluster] = false;
+        splat(&mut removed_energy, cluster, -1.0);
+        rank[cluster] = r;
+    }
+    for r in initial..n {
+        let void = largest_void(&energy, &points);
+        points[void] = true;
+        splat(&mut energy, void, 1.0);
+        rank[void] = r;
+    }
+
+    rank.iter().map(|&r| (r as f64 + 0.5) / n as f64).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The first `n` samples of one pixel, `dimensions` deep.
+    fn samples(kind: SamplerKind, n: u32, dimensions: usize) -> Vec<Vec<f64>> {
+        let mut sampler = kind.build(3, n);
+        (0..n)
+            .map(|index| {
+                sampler.start_pixel_sample(5, 7, index);
+                let mut values = sampler.get_2d().to_vec();
+                values.extend((2..dimensions).map(|_| sampler.get_1d()));
+                values
+            })
+            .collect()
+    }
+
+    fn one_per_stratum(values: impl Iterator<Item = f64>, n: usize) -> bool {
+        let mut strata = vec![0; n];
+        for v in values {
+            strata[(v * n as f64) as usize] += 1;
+        }
+        strata.iter().all(|&count| count == 1)
+    }
+
+    #[test]
+    fn test_samples_are_in_range() {
+        for kind in [
+            SamplerKind::Independent,
+            SamplerKind::Stratified,
+            SamplerKind::Halton,
+            SamplerKind::Sobol,
+            SamplerKind::BlueNoise,
+        ] {
+            let points = samples(kind, 20, 40);
+            assert!(points.iter().flatten().all(|v| (0.0..1.0).contains(v)));
+            assert!(points == samples(kind, 20, 40));
+        }
+    }
+
+    #[test]
+    fn test_samplers_are_stratified() {
+        for kind in [SamplerKind::Stratified, SamplerKind::Sobol] {
+            // A jittered grid stratifies its two dimensions jointly rather than each alone.
+            let points = samples(kind, 16, 6);
+            for d in 2..6 {
+                assert!(one_per_stratum(points.iter().map(|p| p[d]), 16));
+            }
+            let cells = points
+                .iter()
+                .map(|p| ((p[0] * 4.0) as usize + 4 * (p[1] * 4.0) as usize) as f64 / 16.0);
+            assert!(one_per_stratum(cells, 16));
+        }
+        let sobol = samples(SamplerKind::Sobol, 16, 2);
+        assert!(one_per_stratum(sobol.iter().map(|p| p[0]), 16));
+        assert!(one_per_stratum(sobol.iter().map(|p| p[1]), 16));
+        let halton = samples(SamplerKind::Halton, 16, 2);
+        assert!(one_per_stratum(halton.iter().map(|p| p[0]), 16));
+    }
+
+    #[test]
+    fn test_permutation_element() {
+        for l in [1, 5, 16, 100] {
+            let mut seen: Vec<u32> = (0..l).map(|i| permutation_element(i, l, 0x1234)).collect();
+            seen.sort();
+            assert!(seen == (0..l).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_blue_noise_mask() {
+        let mask = blue_noise_mask();
+        let mut sorted = mask.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        assert!(sorted.windows(2).all(|w| w[0] < w[1]));
+
+        // Neighbouring cells of white noise differ by a third on average;
+        // blue noise pushes similar values apart.
+        let neighbour_difference = (0..MASK_SIZE * MASK_SIZE)
+            .map(|p| {
+                let right = p / MASK_SIZE * MASK_SIZE + (p + 1) % MASK_SIZE;
+                (mask[p] - mask[right]).abs()
+            })
+            .sum::<f64>()
+            / (MASK_SIZE * MASK_SIZE) as f64;
+        assert!(neighbour_difference > 0.4);
+    }
+
+    #[test]
+    fn test_warps() {
+        let u = [0.3, 0.8];
+        assert!(sample_uniform_disk(u).length() <= 1.0);
+        assert!((sample_cosine_hemisphere(u).length() - 1.0).abs() < 1e-12);
+        assert!((sample_uniform_sphere(u).length() - 1.0).abs() < 1e-12);
+        let (index, remapped) = split_sample(0.55, 4);
+        assert!(index == 2 && (remapped - 0.2).abs() < 1e-12);
+    }
+}
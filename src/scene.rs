@@ -0,0 +1,1346 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::aov::Aov;
+use crate::bvh::BvhNode;
+use crate::camera::{Background, Camera};
+use crate::colour::Colour;
+use crate::constant_medium::ConstantMedium;
+use crate::denoise::Denoiser;
+use crate::disk::Disk;
+use crate::filter::{Filter, FilterKind};
+use crate::hittable::Hittable;
+use crate::hittable_list::HittableList;
+use crate::instance::Instance;
+use crate::material::{Dielectric, DiffuseLight, Isotropic, Lambertian, Material, Metal};
+use crate::mesh::Mesh;
+use crate::motion::Moving;
+use crate::obj;
+use crate::procedural::{NoiseTexture, Pattern};
+use crate::quad::{self, Quad};
+use crate::rtweekend;
+use crate::sampler::SamplerKind;
+use crate::sphere::Sphere;
+use crate::tagged::Tagged;
+use crate::texture::{CheckerTexture, ImageTexture, SolidColour, Texture};
+use crate::tonemap::{self, ToneMap, ToneOperator};
+use crate::transform::Mat4;
+use crate::triangle::Triangle;
+use crate::vec3::{Point3, Vec3};
+
+/// A world and the camera that renders it, as described by a scene file.
+pub struct Scene {
+    pub world: BvhNode,
+    /// The emitters in `world` that the camera samples directly.
+    pub lights: HittableList,
+    pub camera: Camera,
+    /// How the render is turned into 8-bit output.
+    pub tonemap: ToneMap,
+    /// The denoiser the render is passed through, if any.
+    pub denoiser: Option<Denoiser>,
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(err) => write!(f, "{err}"),
+            SceneError::Parse { line, message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl Error for SceneError {}
+
+impl From<io::Error> for SceneError {
+    fn from(err: io::Error) -> Self {
+        SceneError::Io(err)
+    }
+}
+
+/// Loads a TOML scene file, recording `aovs` as well as the passes it lists.
+/// Paths inside it are relative to the file's directory.
+pub fn load_scene(path: &Path, aovs: &[Aov]) -> Result<Scene, SceneError> {
+    let source = fs::read_to_string(path)?;
+    parse(&source, path.parent().unwrap_or(Path::new("")), aovs)
+}
+
+pub fn parse_scene(source: &str, base_dir: &Path) -> Result<Scene, SceneError> {
+    parse(source, base_dir, &[])
+}
+
+fn parse(source: &str, base_dir: &Path, aovs: &[Aov]) -> Result<Scene, SceneError> {
+    let file: SceneFile = toml::from_str(source).map_err(|err| SceneError::Parse {
+        line: err.span().map_or(1, |span| line_of(source, span.start)),
+        message: err.message().to_string(),
+    })?;
+    SceneBuilder {
+        source,
+        base_dir,
+        aovs,
+        texture_descs: BTreeMap::new(),
+        textures: BTreeMap::new(),
+        building: Vec::new(),
+        materials: BTreeMap::new(),
+        meshes: BTreeMap::new(),
+    }
+    .build(file)
+}
+
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset.min(source.len())].matches('\n').count() + 1
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneFile {
+    #[serde(default)]
+    render: RenderSettings,
+    camera: CameraSettings,
+    #[serde(default)]
+    textures: BTreeMap<String, Spanned<TextureDesc>>,
+    #[serde(default)]
+    materials: BTreeMap<String, Spanned<MaterialDesc>>,
+    #[serde(default)]
+    objects: Vec<Spanned<ObjectEntry>>,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RenderSettings {
+    width: Spanned<i64>,
+    aspect_ratio: Spanned<f64>,
+    samples_per_pixel: Spanned<i32>,
+    adaptive_threshold: Option<Spanned<f64>>,
+    min_samples: Option<Spanned<i32>>,
+    max_depth: Spanned<i32>,
+    roulette_depth: Spanned<i32>,
+    seed: u64,
+    sampler: SamplerDesc,
+    filter: FilterDesc,
+    filter_radius: Option<Spanned<f64>>,
+    exposure: f64,
+    white_balance: Option<f64>,
+    tone_map: ToneMapDesc,
+    white_point: Option<Spanned<f64>>,
+    denoise: bool,
+    aovs: Vec<AovDesc>,
+    background: Option<[f64; 3]>,
+    output: Option<PathBuf>,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            width: Spanned::new(0..0, 400),
+            aspect_ratio: Spanned::new(0..0, 16.0 / 9.0),
+            samples_per_pixel: Spanned::new(0..0, 100),
+            adaptive_threshold: None,
+            min_samples: None,
+            max_depth: Spanned::new(0..0, 50),
+            roulette_depth: Spanned::new(0..0, 3),
+            seed: 0,
+            sampler: SamplerDesc::Independent,
+            filter: FilterDesc::Box,
+            filter_radius: None,
+            exposure: 0.0,
+            white_balance: None,
+            tone_map: ToneMapDesc::Clamp,
+            white_point: None,
+            denoise: false,
+            aovs: Vec::new(),
+            background: None,
+            output: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum SamplerDesc {
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+    BlueNoise,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum FilterDesc {
+    Box,
+    Tent,
+    Gaussian,
+    Mitchell,
+    Lanczos,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ToneMapDesc {
+    Clamp,
+    Reinhard,
+    ExtendedReinhard,
+    Aces,
+    Agx,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum AovDesc {
+    Albedo,
+    Normal,
+    Depth,
+    Position,
+    ObjectId,
+    MaterialId,
+    Direct,
+    Indirect,
+    Variance,
+}
+
+impl AovDesc {
+    fn aov(&self) -> Aov {
+        match self {
+            AovDesc::Albedo => Aov::Albedo,
+            AovDesc::Normal => Aov::Normal,
+            AovDesc::Depth => Aov::Depth,
+            AovDesc::Position => Aov::Position,
+            AovDesc::ObjectId => Aov::ObjectId,
+            AovDesc::MaterialId => Aov::MaterialId,
+            AovDesc::Direct => Aov::Direct,
+            AovDesc::Indirect => Aov::Indirect,
+            AovDesc::Variance => Aov::Variance,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraSettings {
+    look_from: [f64; 3],
+    look_at: [f64; 3],
+    #[serde(default = "default_vup")]
+    vup: [f64; 3],
+    #[serde(default = "default_vfov")]
+    vfov: f64,
+    #[serde(default)]
+    defocus_angle: f64,
+    #[serde(default = "default_focus_dist")]
+    focus_dist: f64,
+    #[serde(default)]
+    shutter_open: f64,
+    #[serde(default = "default_shutter_close")]
+    shutter_close: f64,
+}
+
+fn default_vup() -> [f64; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+fn default_vfov() -> f64 {
+    90.0
+}
+
+fn default_focus_dist() -> f64 {
+    10.0
+}
+
+fn default_shutter_close() -> f64 {
+    1.0
+}
+
+/// A colour given either inline as `[r, g, b]` or as the name of a texture.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ColourDesc {
+    Rgb([f64; 3]),
+    Texture(String),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum TextureDesc {
+    Solid {
+        colour: [f64; 3],
+    },
+    Checker {
+        scale: f64,
+        even: ColourDesc,
+        odd: ColourDesc,
+    },
+    Image {
+        path: String,
+    },
+    Noise {
+        pattern: PatternDesc,
+        #[serde(default = "default_scale")]
+        scale: f64,
+        #[serde(default)]
+        seed: u64,
+        low: Option<[f64; 3]>,
+        high: Option<[f64; 3]>,
+        /// Octaves of turbulence, or of fBm.
+        octaves: Option<u32>,
+        #[serde(default = "default_lacunarity")]
+        lacunarity: f64,
+        #[serde(default = "default_gain")]
+        gain: f64,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum PatternDesc {
+    Noise,
+    Turbulence,
+    Marble,
+    Wood,
+    Voronoi,
+    Fbm,
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+fn default_lacunarity() -> f64 {
+    2.0
+}
+
+fn default_gain() -> f64 {
+    0.5
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum MaterialDesc {
+    Lambertian {
+        albedo: ColourDesc,
+    },
+    Metal {
+        albedo: ColourDesc,
+        #[serde(default)]
+        fuzz: f64,
+    },
+    Dielectric {
+        ir: f64,
+    },
+    DiffuseLight {
+        emit: ColourDesc,
+    },
+    Isotropic {
+        albedo: ColourDesc,
+    },
+}
+
+/// An object together with the settings shared by every object type.
+#[derive(Deserialize)]
+struct ObjectEntry {
+    #[serde(flatten)]
+    shape: ObjectDesc,
+    /// Scale factor, uniform or per axis, applied first.
+    scale: Option<ScaleDesc>,
+    /// Rotations in degrees about the x, y and z axes, applied in that order.
+    rotate: Option<[f64; 3]>,
+    translate: Option<[f64; 3]>,
+    /// Turns the object into the boundary of a volume of this density,
+    /// scattering with the object's material.
+    density: Option<f64>,
+    /// Distance the object moves between times 0 and 1.
+    motion: Option<[f64; 3]>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScaleDesc {
+    Uniform(f64),
+    PerAxis([f64; 3]),
+}
+
+impl ObjectEntry {
+    fn transform(&self) -> Option<Mat4> {
+        if self.scale.is_none() && self.rotate.is_none() && self.translate.is_none() {
+            return None;
+        }
+
+        let scale = match self.scale {
+            Some(ScaleDesc::Uniform(factor)) => Vec3::new(factor, factor, factor),
+            Some(ScaleDesc::PerAxis(factors)) => vec3(factors),
+            None => Vec3::new(1.0, 1.0, 1.0),
+        };
+        let [rx, ry, rz] = self.rotate.unwrap_or_default();
+        let rotation = Mat4::rotation(Vec3::new(0.0, 0.0, 1.0), rz)
+            * Mat4::rotation(Vec3::new(0.0, 1.0, 0.0), ry)
+            * Mat4::rotation(Vec3::new(1.0, 0.0, 0.0), rx);
+        let translation = Mat4::translation(vec3(self.translate.unwrap_or_default()));
+        Some(translation * rotation * Mat4::scaling(scale))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ObjectDesc {
+    Sphere {
+        center: [f64; 3],
+        radius: f64,
+        material: String,
+    },
+    Quad {
+        q: [f64; 3],
+        u: [f64; 3],
+        v: [f64; 3],
+        material: String,
+    },
+    Triangle {
+        a: [f64; 3],
+        b: [f64; 3],
+        c: [f64; 3],
+        material: String,
+    },
+    Disk {
+        center: [f64; 3],
+        normal: [f64; 3],
+        radius: f64,
+        material: String,
+    },
+    Box {
+        a: [f64; 3],
+        b: [f64; 3],
+        material: String,
+    },
+    Obj {
+        path: String,
+        material: String,
+        /// Materials for named OBJ groups, overriding `material`.
+        #[serde(default)]
+        groups: BTreeMap<String, String>,
+    },
+}
+
+impl ObjectDesc {
+    fn material(&self) -> &str {
+        match self {
+            ObjectDesc::Sphere { material, .. }
+            | ObjectDesc::Quad { material, .. }
+            | ObjectDesc::Triangle { material, .. }
+            | ObjectDesc::Disk { material, .. }
+            | ObjectDesc::Box { material, .. }
+            | ObjectDesc::Obj { material, .. } => material,
+        }
+    }
+
+    /// Every material the object's surfaces may use.
+    fn materials(&self) -> Vec<&str> {
+        match self {
+            ObjectDesc::Obj { groups, .. } => std::iter::once(self.material())
+                .chain(groups.values().map(String::as_str))
+                .collect(),
+            _ => vec![self.material()],
+        }
+    }
+}
+
+fn vec3(v: [f64; 3]) -> Vec3 {
+    Vec3::new(v[0], v[1], v[2])
+}
+
+struct SceneBuilder<'a> {
+    source: &'a str,
+    base_dir: &'a Path,
+    /// Passes asked for outside the file.
+    aovs: &'a [Aov],
+    texture_descs: BTreeMap<String, Spanned<TextureDesc>>,
+    textures: BTreeMap<String, Arc<dyn Texture>>,
+    // The textures still being built, innermost last, to catch and report cycles.
+    building: Vec<String>,
+    materials: BTreeMap<String, Arc<dyn Material>>,
+    meshes: BTreeMap<MeshKey, Arc<dyn Hittable>>,
+}
+
+/// OBJ objects with the same file and materials share one mesh, so that instancing it is cheap.
+type MeshKey = (String, String, BTreeMap<String, String>);
+
+impl SceneBuilder<'_> {
+    fn build(mut self, file: SceneFile) -> Result<Scene, SceneError> {
+        let texture_names: Vec<String> = file.textures.keys().cloned().collect();
+        self.texture_descs = file.textures;
+        for name in texture_names {
+            if let Some(desc) = self.texture_descs.get(&name) {
+                let line = line_of(self.source, desc.span().start);
+                self.texture(&name, line)?;
+            }
+        }
+        let mut emitters = BTreeSet::new();
+        let mut isotropic = BTreeSet::new();
+        for (name, desc) in file.materials {
+            let line = line_of(self.source, desc.span().start);
+            let desc = desc.into_inner();
+            match desc {
+                MaterialDesc::DiffuseLight { .. } => emitters.insert(name.clone()),
+                MaterialDesc::Isotropic { .. } => isotropic.insert(name.clone()),
+                _ => false,
+            };
+            let material = self.material(desc, line)?;
+            self.materials.insert(name, material);
+        }
+
+        let mut aovs: Vec<Aov> = file.render.aovs.iter().map(AovDesc::aov).collect();
+        for aov in self.aovs {
+            if !aovs.contains(aov) {
+                aovs.push(*aov);
+            }
+        }
+        // Materials are numbered in name order and objects in file order, for the ID passes.
+        let tag_ids = aovs.contains(&Aov::ObjectId) || aovs.contains(&Aov::MaterialId);
+        let material_ids: BTreeMap<String, u32> = self.materials.keys().cloned().zip(1..).collect();
+        let mut world = HittableList::default();
+        let mut lights = HittableList::default();
+        for (index, desc) in file.objects.into_iter().enumerate() {
+            let line = line_of(self.source, desc.span().start);
+            let entry = desc.into_inner();
+            let transform = entry.transform();
+            let material = entry.shape.material().to_string();
+            let object_materials: Vec<(Arc<dyn Material>, u32)> = entry
+                .shape
+                .materials()
+                .into_iter()
+                .filter_map(|name| Some((self.materials.get(name)?.clone(), material_ids[name])))
+                .collect();
+            // Meshes have no way to sample points on themselves, and moving
+            // lights would be sampled where they are at time 0.
+            let is_light = emitters.contains(&material)
+                && entry.motion.is_none()
+                && !matches!(entry.shape, ObjectDesc::Obj { .. });
+            let mut object = self.object(entry.shape, line)?;
+            if let Some(density) = entry.density {
+                if density <= 0.0 {
+                    return Err(SceneError::Parse {
+                        line,
+                        message: String::from("density must be greater than 0"),
+                    });
+                }
+                if !isotropic.contains(&material) {
+                    return Err(SceneError::Parse {
+                        line,
+                        message: format!("a volume needs an isotropic material, not '{material}'"),
+                    });
+                }
+                let phase_function = self.lookup(&material, line)?;
+                object = Arc::new(ConstantMedium::with_phase_function(
+                    object,
+                    density,
+                    phase_function,
+                ));
+            }
+            if let Some(transform) = transform {
+                if transform.inverse().is_none() {
+                    return Err(SceneError::Parse {
+                        line,
+                        message: String::from("an object cannot be scaled by 0"),
+                    });
+                }
+                object = Arc::new(Instance::new(object, transform));
+            }
+            if let Some(motion) = entry.motion {
+                object = Arc::new(Moving::new(object, vec3(motion)));
+            }
+            if tag_ids {
+                object = Arc::new(Tagged::new(object, index as u32 + 1, object_materials));
+            }
+            if is_light {
+                lights.add(object.clone());
+            }
+            world.add(object);
+        }
+        if world.is_empty() {
+            return Err(SceneError::Parse {
+                line: line_of(self.source, self.source.len()),
+                message: String::from("the scene has no objects"),
+            });
+        }
+
+        let mut render = file.render;
+        let cam = file.camera;
+        let mut camera = Camera::new(
+            self.positive("aspect_ratio", render.aspect_ratio)?,
+            self.positive("width", render.width)?,
+            self.positive("samples_per_pixel", render.samples_per_pixel)?,
+            self.positive("max_depth", render.max_depth)?,
+            cam.vfov,
+            vec3(cam.look_from),
+            vec3(cam.look_at),
+            vec3(cam.vup),
+            cam.defocus_angle,
+            cam.focus_dist,
+        );
+        camera.seed = render.seed;
+        camera.sampler = match render.sampler {
+            SamplerDesc::Independent => SamplerKind::Independent,
+            SamplerDesc::Stratified => SamplerKind::Stratified,
+            SamplerDesc::Halton => SamplerKind::Halton,
+            SamplerDesc::Sobol => SamplerKind::Sobol,
+            SamplerDesc::BlueNoise => SamplerKind::BlueNoise,
+        };
+        let filter = match render.filter {
+            FilterDesc::Box => FilterKind::Box,
+            FilterDesc::Tent => FilterKind::Tent,
+            FilterDesc::Gaussian => FilterKind::Gaussian,
+            FilterDesc::Mitchell => FilterKind::Mitchell,
+            FilterDesc::Lanczos => FilterKind::Lanczos,
+        };
+        camera.filter = match render.filter_radius {
+            Some(radius) => {
+                let line = line_of(self.source, radius.span().start);
+                let radius = self.positive("filter_radius", radius)?;
+                if radius > Filter::MAX_RADIUS {
+                    return Err(SceneError::Parse {
+                        line,
+                        message: format!("filter_radius must be at most {}", Filter::MAX_RADIUS),
+                    });
+                }
+                Filter::with_radius(filter, radius)
+            }
+            None => Filter::new(filter),
+        };
+        camera.roulette_depth = self.at_least("roulette_depth", render.roulette_depth, 0)?;
+        camera.adaptive_threshold = render
+            .adaptive_threshold
+            .map(|threshold| self.positive("adaptive_threshold", threshold))
+            .transpose()?;
+        if let Some(min_samples) = render.min_samples {
+            let line = line_of(self.source, min_samples.span().start);
+            camera.min_samples = self.at_least("min_samples", min_samples, 2)?;
+            if camera.min_samples > camera.samples_per_pixel {
+                return Err(SceneError::Parse {
+                    line,
+                    message: String::from("min_samples must be at most samples_per_pixel"),
+                });
+            }
+        }
+        camera.aovs = aovs;
+        camera.shutter_open = cam.shutter_open;
+        camera.shutter_close = cam.shutter_close;
+        let operator = match render.tone_map {
+            ToneMapDesc::Clamp => ToneOperator::Clamp,
+            ToneMapDesc::Reinhard => ToneOperator::Reinhard,
+            ToneMapDesc::ExtendedReinhard => ToneOperator::ExtendedReinhard {
+                white: match render.white_point.take() {
+                    Some(white_point) => self.positive("white_point", white_point)?,
+                    None => tonemap::DEFAULT_WHITE_POINT,
+                },
+            },
+            ToneMapDesc::Aces => ToneOperator::Aces,
+            ToneMapDesc::Agx => ToneOperator::Agx,
+        };
+        if let Some(white_point) = render.white_point {
+            return Err(SceneError::Parse {
+                line: line_of(self.source, white_point.span().start),
+                message: String::from("white_point needs tone_map = \"extended_reinhard\""),
+            });
+        }
+        let mut tonemap = ToneMap::new(render.exposure, operator);
+        tonemap.set_white_balance(render.white_balance);
+        if let Some(background) = render.background {
+            camera.background = Background::Solid(vec3(background));
+        }
+
+        Ok(Scene {
+            world: BvhNode::from_list(world),
+            lights,
+            camera,
+            tonemap,
+            denoiser: render.denoise.then(Denoiser::default),
+            output: render.output,
+        })
+    }
+
+    /// The value of a setting that must be greater than 0, or an error at its line.
+    fn positive<T: PartialOrd + Default>(
+        &self,
+        name: &str,
+        value: Spanned<T>,
+    ) -> Result<T, SceneError> {
+        if *value.get_ref() > T::default() {
+            return Ok(value.into_inner());
+        }
+        Err(SceneError::Parse {
+            line: line_of(self.source, value.span().start),
+            message: format!("{name} must be greater than 0"),
+        })
+    }
+
+    /// The value of a setting that must be at least `min`, or an error at its line.
+    fn at_least<T: PartialOrd + fmt::Display>(
+        &self,
+        name: &str,
+        value: Spanned<T>,
+        min: T,
+    ) -> Result<T, SceneError> {
+        if *value.get_ref() >= min {
+            return Ok(value.into_inner());
+        }
+        Err(SceneError::Parse {
+            line: line_of(self.source, value.span().start),
+            message: format!("{name} must be at least {min}"),
+        })
+    }
+
+    fn material(
+        &mut self,
+        desc: MaterialDesc,
+        line: usize,
+    ) -> Result<Arc<dyn Material>, SceneError> {
+        Ok(match desc {
+            MaterialDesc::Lambertian { albedo } => {
+                Arc::new(Lambertian::from_texture(self.colour(albedo, line)?))
+            }
+            MaterialDesc::Metal { albedo, fuzz } => {
+                Arc::new(Metal::from_texture(self.colour(albedo, line)?, fuzz))
+            }
+            MaterialDesc::Dielectric { ir } => Arc::new(Dielectric { ir }),
+            MaterialDesc::DiffuseLight { emit } => {
+                Arc::new(DiffuseLight::from_texture(self.colour(emit, line)?))
+            }
+            MaterialDesc::Isotropic { albedo } => {
+                Arc::new(Isotropic::from_texture(self.colour(albedo, line)?))
+            }
+        })
+    }
+
+    fn colour(&mut self, desc: ColourDesc, line: usize) -> Result<Arc<dyn Texture>, SceneError> {
+        match desc {
+            ColourDesc::Rgb(rgb) => Ok(Arc::new(SolidColour::new(vec3(rgb)))),
+            ColourDesc::Texture(name) => self.texture(&name, line),
+        }
+    }
+
+    fn texture(&mut self, name: &str, line: usize) -> Result<Arc<dyn Texture>, SceneError> {
+        if let Some(texture) = self.textures.get(name) {
+            return Ok(texture.clone());
+        }
+        if let Some(start) = self.building.iter().position(|building| building == name) {
+            let message = match &self.building[start..] {
+                [_] => format!("texture '{name}' refers to itself"),
+                cycle => format!(
+                    "textures refer to each other in a cycle: {} -> '{name}'",
+                    cycle
+                        .iter()
+                        .map(|texture| format!("'{texture}'"))
+                        .collect::<Vec<_>>()
+                        .join(" -> ")
+                ),
+            };
+            return Err(SceneError::Parse { line, message });
+        }
+        let Some(desc) = self.texture_descs.remove(name) else {
+            return Err(SceneError::Parse {
+                line,
+                message: format!("unknown texture '{name}'"),
+            });
+        };
+
+        self.building.push(name.to_string());
+        let line = line_of(self.source, desc.span().start);
+        let texture: Arc<dyn Texture> = match desc.into_inner() {
+            TextureDesc::Solid { colour } => Arc::new(SolidColour::new(vec3(colour))),
+            TextureDesc::Checker { scale, even, odd } => {
+                if scale <= 0.0 {
+                    return Err(SceneError::Parse {
+                        line,
+                        message: String::from("checker scale must be greater than 0"),
+                    });
+                }
+                let even = self.colour(even, line)?;
+                let odd = self.colour(odd, line)?;
+                Arc::new(CheckerTexture::new(scale, even, odd))
+            }
+            TextureDesc::Image { path } => {
+                let path = self.base_dir.join(path);
+                Arc::new(ImageTexture::load(&path).map_err(|err| SceneError::Parse {
+                    line,
+                    message: format!("unable to load {}: {err}", path.display()),
+                })?)
+            }
+            TextureDesc::Noise {
+                pattern,
+                scale,
+                seed,
+                low,
+                high,
+                octaves,
+                lacunarity,
+                gain,
+            } => {
+                let pattern = match pattern {
+                    PatternDesc::Noise => Pattern::Noise,
+                    PatternDesc::Turbulence => Pattern::Turbulence {
+                        depth: octaves.unwrap_or(7),
+                    },
+                    PatternDesc::Marble => Pattern::Marble {
+                        depth: octaves.unwrap_or(7),
+                    },
+                    PatternDesc::Wood => Pattern::Wood {
+                        depth: octaves.unwrap_or(3),
+                    },
+                    PatternDesc::Voronoi => Pattern::Voronoi,
+                    PatternDesc::Fbm => Pattern::Fbm {
+                        octaves: octaves.unwrap_or(5),
+                        lacunarity,
+                        gain,
+                    },
+                };
+                Arc::new(NoiseTexture::with_colours(
+                    pattern,
+                    scale,
+                    seed,
+                    vec3(low.unwrap_or([0.0, 0.0, 0.0])),
+                    vec3(high.unwrap_or([1.0, 1.0, 1.0])),
+                ))
+            }
+        };
+        self.building.pop();
+        self.textures.insert(name.to_string(), texture.clone());
+        Ok(texture)
+    }
+
+    fn lookup(&self, name: &str, line: usize) -> Result<Arc<dyn Material>, SceneError> {
+        self.materials
+            .get(name)
+            .cloned()
+            .ok_or_else(|| SceneError::Parse {
+                line,
+                message: format!("unknown material '{name}'"),
+            })
+    }
+
+    fn object(&mut self, desc: ObjectDesc, line: usize) -> Result<Arc<dyn Hittable>, SceneError> {
+        Ok(match desc {
+            ObjectDesc::Sphere {
+                center,
+                radius,
+                material,
+            } => Arc::new(Sphere::new(
+                vec3(center),
+                radius,
+                self.lookup(&material, line)?,
+            )),
+            ObjectDesc::Quad { q, u, v, material } => Arc::new(Quad::new(
+                vec3(q),
+                vec3(u),
+                vec3(v),
+                self.lookup(&material, line)?,
+            )),
+            ObjectDesc::Triangle { a, b, c, material } => Arc::new(Triangle::new(
+                vec3(a),
+                vec3(b),
+                vec3(c),
+                self.lookup(&material, line)?,
+            )),
+            ObjectDesc::Disk {
+                center,
+                normal,
+                radius,
+                material,
+            } => Arc::new(Disk::new(
+                vec3(center),
+                vec3(normal),
+                radius,
+                self.lookup(&material, line)?,
+            )),
+            ObjectDesc::Box { a, b, material } => Arc::new(quad::make_box(
+                vec3(a),
+                vec3(b),
+                self.lookup(&material, line)?,
+            )),
+            ObjectDesc::Obj {
+                path,
+                material,
+                groups,
+            } => {
+                let key = (path, material, groups);
+                if let Some(mesh) = self.meshes.get(&key) {
+                    return Ok(mesh.clone());
+                }
+                let (path, material, groups) = &key;
+
+                let data =
+                    obj::load_obj(&self.base_dir.join(path)).map_err(|err| SceneError::Parse {
+                        line,
+                        message: format!("unable to load '{}': {err}", path),
+                    })?;
+                if data.faces.is_empty() {
+                    return Err(SceneError::Parse {
+                        line,
+                        message: format!("'{}' has no faces", path),
+                    });
+                }
+
+                let default = self.lookup(material, line)?;
+                let mut group_materials = BTreeMap::new();
+                for (group, name) in groups {
+                    group_materials.insert(group.clone(), self.lookup(name, line)?);
+                }
+                let mesh: Arc<dyn Hittable> = Arc::new(Mesh::with_group_materials(data, |group| {
+                    group_materials
+                        .get(group)
+                        .cloned()
+                        .unwrap_or_else(|| default.clone())
+                }));
+                self.meshes.insert(key, mesh.clone());
+                mesh
+            }
+        })
+    }
+}
+
+/// The final scene from the first book: three large spheres among a random
+/// field of small ones, recording `aovs`.
+pub fn random_spheres(seed: u64, aovs: &[Aov]) -> Scene {
+    let mut rng = rtweekend::seeded_rng(seed);
+
+    let ground_material = Arc::new(Lambertian::new(Colour::new(0.5, 0.5, 0.5)));
+    let mut spheres: Vec<(Point3, f64, Arc<dyn Material>)> =
+        vec![(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground_material)];
+
+    for a in -11..11 {
+        for b in -11..11 {
+            let choose_mat = rtweekend::random_float(&mut rng);
+            let center = Point3::new(
+                a as f64 + 0.9 * rtweekend::random_float(&mut rng),
+                0.2,
+                b as f64 + 0.9 * rtweekend::random_float(&mut rng),
+            );
+
+            if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
+                if choose_mat < 0.8 {
+                    let albedo = Colour::random(&mut rng) * Colour::random(&mut rng);
+                    let sphere_material = Arc::new(Lambertian::new(albedo));
+                    spheres.push((center, 0.2, sphere_material));
+                } else if choose_mat < 0.95 {
+                    let albedo = Colour::random_range(0.5, 1.0, &mut rng);
+                    let fuzz = rtweekend::random_float_range(0.0, 0.5, &mut rng);
+                    let sphere_material = Arc::new(Metal::new(albedo, fuzz));
+                    spheres.push((center, 0.2, sphere_material));
+                } else {
+                    let sphere_material = Arc::new(Dielectric { ir: 1.5 });
+                    spheres.push((center, 0.2, sphere_material));
+                }
+            }
+        }
+    }
+
+    let material1 = Arc::new(Dielectric { ir: 1.5 });
+    spheres.push((Point3::new(0.0, 1.0, 0.0), 1.0, material1));
+
+    let material2 = Arc::new(Lambertian::from_texture(Arc::new(
+        NoiseTexture::with_colours(
+            Pattern::Marble { depth: 7 },
+            4.0,
+            seed,
+            Colour::new(0.2, 0.1, 0.05),
+            Colour::new(0.6, 0.35, 0.2),
+        ),
+    )));
+    spheres.push((Point3::new(-4.0, 1.0, 0.0), 1.0, material2));
+
+    let material3 = Arc::new(Metal::new(Colour::new(0.7, 0.6, 0.5), 0.0));
+    spheres.push((Point3::new(4.0, 1.0, 0.0), 1.0, material3));
+
+    // Every sphere has a material of its own, so both are numbered in the order they were made.
+    let tag_ids = aovs.contains(&Aov::ObjectId) || aovs.contains(&Aov::MaterialId);
+    let mut world = HittableList::default();
+    for (index, (center, radius, mat)) in spheres.into_iter().enumerate() {
+        let mut sphere: Arc<dyn Hittable> = Arc::new(Sphere::new(center, radius, mat.clone()));
+        if tag_ids {
+            let id = index as u32 + 1;
+            sphere = Arc::new(Tagged::new(sphere, id, vec![(mat, id)]));
+        }
+        world.add(sphere);
+    }
+
+    let mut cam = Camera::new(
+        16.0 / 9.0,
+        1200,
+        500,
+        50,
+        20.0,
+        Point3::new(13.0, 2.0, 3.0),
+        Point3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        0.6,
+        10.0,
+    );
+    cam.seed = seed;
+    for aov in aovs {
+        if !cam.aovs.contains(aov) {
+            cam.aovs.push(*aov);
+        }
+    }
+
+    Scene {
+        world: BvhNode::from_list(world),
+        lights: HittableList::default(),
+        camera: cam,
+        tonemap: ToneMap::default(),
+        denoiser: None,
+        output: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::HitRecord;
+    use crate::interval::Interval;
+    use crate::ray::Ray;
+
+    const SCENE: &str = r#"
+[render]
+width = 64
+samples_per_pixel = 4
+background = [0.0, 0.0, 0.0]
+
+[camera]
+look_from = [0.0, 0.0, 5.0]
+look_at = [0.0, 0.0, 0.0]
+
+[materials.red]
+type = "lambertian"
+albedo = [0.65, 0.05, 0.05]
+
+[materials.light]
+type = "diffuse_light"
+emit = [15.0, 15.0, 15.0]
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = "red"
+
+[[objects]]
+type = "quad"
+motion = [0.0, 0.5, 0.0]
+q = [-1.0, 2.0, -1.0]
+u = [2.0, 0.0, 0.0]
+v = [0.0, 0.0, 2.0]
+material = "light"
+"#;
+
+    #[test]
+    fn test_parse_scene() {
+        let scene = parse_scene(SCENE, Path::new("")).unwrap();
+        assert!(scene.camera.image_width == 64);
+        assert!(scene.camera.samples_per_pixel == 4);
+        assert!(scene.camera.background == Background::Solid(Colour::new(0.0, 0.0, 0.0)));
+        assert!(scene.world.bounding_box().y.max > 2.49);
+        assert!(scene.output.is_none());
+        assert!(scene.lights.is_empty());
+
+        let source = SCENE.replace("motion = [0.0, 0.5, 0.0]\n", "");
+        let scene = parse_scene(&source, Path::new("")).unwrap();
+        assert!(scene.lights.len() == 1);
+    }
+
+    #[test]
+    fn test_unknown_material_reports_line() {
+        let source = SCENE.replace("material = \"light\"", "material = \"lamp\"");
+        let err = parse_scene(&source, Path::new("")).err().unwrap();
+        assert!(err.to_string() == "line 25: unknown material 'lamp'");
+    }
+
+    #[test]
+    fn test_volumes_need_isotropic_material() {
+        let source = SCENE.replace("radius = 1.0", "radius = 1.0\ndensity = 0.5");
+        let err = parse_scene(&source, Path::new("")).err().unwrap();
+        assert!(err.to_string() == "line 19: a volume needs an isotropic material, not 'red'");
+
+        let source = source.replace(
+            "type = \"lambertian\"\nalbedo = [0.65, 0.05, 0.05]",
+            "type = \"isotropic\"\nalbedo = [0.65, 0.05, 0.05]",
+        );
+        assert!(parse_scene(&source, Path::new("")).is_ok());
+    }
+
+    #[test]
+    fn test_syntax_error_reports_line() {
+        let source = SCENE.replace("radius = 1.0", "radius = ");
+        let err = parse_scene(&source, Path::new("")).err().unwrap();
+        assert!(matches!(err, SceneError::Parse { line: 22, .. }));
+
+        let source = SCENE.replace("type = \"sphere\"", "type = \"cone\"");
+        let err = parse_scene(&source, Path::new("")).err().unwrap();
+        assert!(matches!(err, SceneError::Parse { line: 19, .. }));
+    }
+
+    #[test]
+    fn test_transforms() {
+        let source = SCENE.replace(
+            "radius = 1.0",
+            "radius = 1.0\nscale = [1.0, 1.0, 3.0]\nrotate = [90.0, 0.0, 0.0]\ntranslate = [0.0, -4.0, 0.0]",
+        );
+        let scene = parse_scene(&source, Path::new("")).unwrap();
+        assert!((scene.world.bounding_box().y.min + 7.0).abs() < 1e-9);
+
+        let source = SCENE.replace("radius = 1.0", "radius = 1.0\nscale = 0.0");
+        let err = parse_scene(&source, Path::new("")).err().unwrap();
+        assert!(err.to_string() == "line 19: an object cannot be scaled by 0");
+    }
+
+    #[test]
+    fn test_sampling_settings() {
+        let source = SCENE.replace(
+            "samples_per_pixel = 4",
+            "samples_per_pixel = 4\nsampler = \"blue_noise\"\nfilter = \"mitchell\"\ndenoise = true",
+        );
+        let scene = parse_scene(&source, Path::new("")).unwrap();
+        assert!(scene.camera.sampler == SamplerKind::BlueNoise);
+        assert!(scene.camera.filter == Filter::new(FilterKind::Mitchell));
+        assert!(scene.tonemap == ToneMap::default());
+        assert!(scene.denoiser == Some(Denoiser::default()));
+
+        let source = SCENE.replace(
+            "samples_per_pixel = 4",
+            "samples_per_pixel = 4\nfilter = \"gaussian\"\nfilter_radius = 0.0",
+        );
+        let err = parse_scene(&source, Path::new("")).err().unwrap();
+        assert!(err.to_string() == "line 6: filter_radius must be greater than 0");
+
+        let source = source.replace("filter_radius = 0.0", "filter_radius = 100000.0");
+        let err = parse_scene(&source, Path::new("")).err().unwrap();
+        assert!(err.to_string() == "line 6: filter_radius must be at most 8");
+    }
+
+    #[test]
+    fn test_render_settings_must_be_positive() {
+        for (from, to, line) in [
+            ("width = 64", "width = -5", 3),
+            ("samples_per_pixel = 4", "samples_per_pixel = 0", 4),
+            (
+                "samples_per_pixel = 4",
+                "samples_per_pixel = 4\naspect_ratio = 0.0",
+                5,
+            ),
+            (
+                "samples_per_pixel = 4",
+                "samples_per_pixel = 4\nmax_depth = -1",
+                5,
+            ),
+        ] {
+            let setting = to.lines().last().unwrap().split(' ').next().unwrap();
+            let err = parse_scene(&SCENE.replace(from, to), Path::new(""))
+                .err()
+                .unwrap();
+            assert!(err.to_string() == format!("line {line}: {setting} must be greater than 0"));
+        }
+
+        for (to, message) in [
+            (
+                "adaptive_threshold = -0.1",
+                "adaptive_threshold must be greater than 0",
+            ),
+            ("min_samples = 1", "min_samples must be at least 2"),
+            (
+                "min_samples = 8",
+                "min_samples must be at most samples_per_pixel",
+            ),
+            ("roulette_depth = -1", "roulette_depth must be at least 0"),
+        ] {
+            let source = SCENE.replace(
+                "samples_per_pixel = 4",
+                &format!("samples_per_pixel = 4\n{to}"),
+            );
+            let err = parse_scene(&source, Path::new("")).err().unwrap();
+            assert!(err.to_string() == format!("line 5: {message}"));
+        }
+        let source = SCENE.replace(
+            "samples_per_pixel = 4",
+            "samples_per_pixel = 4\nadaptive_threshold = 0.1\nmin_samples = 4\nroulette_depth = 0",
+        );
+        let scene = parse_scene(&source, Path::new("")).unwrap();
+        assert!(scene.camera.adaptive_threshold == Some(0.1));
+        assert!(scene.camera.min_samples == 4 && scene.camera.roulette_depth == 0);
+    }
+
+    #[test]
+    fn test_tone_map_settings() {
+        let source = SCENE.replace(
+            "samples_per_pixel = 4",
+            "samples_per_pixel = 4\nexposure = -1.5\ntone_map = \"extended_reinhard\"\nwhite_point = 8.0",
+        );
+        let scene = parse_scene(&source, Path::new("")).unwrap();
+        assert!(scene.tonemap.exposure == -1.5);
+        assert!(scene.tonemap.operator == ToneOperator::ExtendedReinhard { white: 8.0 });
+
+        let source = SCENE.replace(
+            "samples_per_pixel = 4",
+            "samples_per_pixel = 4\ntone_map = \"aces\"\nwhite_point = 8.0",
+        );
+        let err = parse_scene(&source, Path::new("")).err().unwrap();
+        assert!(err.to_string() == "line 6: white_point needs tone_map = \"extended_reinhard\"");
+
+        let source = SCENE.replace(
+            "samples_per_pixel = 4",
+            "samples_per_pixel = 4\ntone_map = \"extended_reinhard\"\nwhite_point = 0.0",
+        );
+        let err = parse_scene(&source, Path::new("")).err().unwrap();
+        assert!(err.to_string() == "line 6: white_point must be greater than 0");
+    }
+
+    #[test]
+    fn test_aov_settings() {
+        let source = SCENE.replace(
+            "samples_per_pixel = 4",
+            "samples_per_pixel = 1\naovs = [\"object_id\", \"material_id\"]",
+        );
+        let mut scene = parse_scene(&source, Path::new("")).unwrap();
+        assert!(scene.camera.aovs == [Aov::ObjectId, Aov::MaterialId]);
+
+        scene.camera.image_width = 16;
+        let layers = scene.camera.render_layers(&scene.world, &scene.lights);
+        let object_id = layers.layer(Aov::ObjectId).unwrap();
+        let material_id = layers.layer(Aov::MaterialId).unwrap();
+        // Materials are numbered in name order, so "light" is 1 and "red" is 2.
+        assert!(object_id.pixel(8, 4) == Colour::new(1.0, 1.0, 1.0));
+        assert!(material_id.pixel(8, 4) == Colour::new(2.0, 2.0, 2.0));
+        assert!(object_id.pixel(0, 8) == Colour::default());
+    }
+
+    #[test]
+    fn test_objects_are_tagged_only_for_id_passes() {
+        let ids = |scene: &Scene| {
+            let r = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+            let mut rec = HitRecord::default();
+            assert!(scene
+                .world
+                .hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+            (rec.object_id, rec.material_id)
+        };
+        assert!(ids(&parse_scene(SCENE, Path::new("")).unwrap()) == (0, 0));
+
+        let scene = parse(SCENE, Path::new(""), &[Aov::MaterialId]).unwrap();
+        assert!(scene.camera.aovs == [Aov::MaterialId]);
+        assert!(ids(&scene) == (1, 2));
+
+        // The built-in scene numbers each sphere and its material alike, from the ground up.
+        let ground = Ray::new(Point3::new(50.0, 1.0, 50.0), Vec3::new(0.0, -1.0, 0.0));
+        for (aovs, id) in [(vec![], 0), (vec![Aov::ObjectId], 1)] {
+            let scene = random_spheres(0, &aovs);
+            let mut rec = HitRecord::default();
+            assert!(scene
+                .world
+                .hit(&ground, Interval::new(0.001, f64::INFINITY), &mut rec));
+            assert!(rec.object_id == id && rec.material_id == id);
+        }
+    }
+
+    #[test]
+    fn test_obj_groups_have_their_own_material_ids() {
+        let dir = std::env::temp_dir().join(format!("scene-obj-groups-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let panels = "v -2 -1 0\nv 0 -1 0\nv 0 1 0\nv -2 1 0\nv 2 -1 0\nv 2 1 0\n\
+                      g left\nf 1 2 3 4\ng right\nf 2 5 6 3\n";
+        fs::write(dir.join("panels.obj"), panels).unwrap();
+        let source = format!(
+            "{SCENE}{}",
+            r#"
+[materials.blue]
+type = "lambertian"
+albedo = [0.05, 0.05, 0.65]
+
+[[objects]]
+type = "obj"
+path = "panels.obj"
+material = "red"
+groups = { right = "blue" }
+translate = [0.0, 0.0, 3.0]
+"#
+        );
+        let scene = parse(&source, &dir, &[Aov::MaterialId]).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        // By name order "blue" is 1, "light" 2 and "red" 3.
+        for (x, material_id) in [(-1.0, 3), (1.0, 1)] {
+            let r = Ray::new(Point3::new(x, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+            let mut rec = HitRecord::default();
+            assert!(scene
+                .world
+                .hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+            assert!(rec.object_id == 3 && rec.material_id == material_id);
+        }
+    }
+
+    #[test]
+    fn test_textures() {
+        let textures = r#"
+[textures.tiles]
+type = "checker"
+scale = 0.5
+even = [1.0, 1.0, 1.0]
+odd = "dark"
+
+[textures.dark]
+type = "solid"
+colour = [0.1, 0.1, 0.1]
+
+[textures.marble]
+type = "noise"
+pattern = "marble"
+scale = 4.0
+seed = 5
+"#;
+        let source = SCENE.replace("albedo = [0.65, 0.05, 0.05]", "albedo = \"tiles\"");
+        assert!(parse_scene(&format!("{source}{textures}"), Path::new("")).is_ok());
+
+        let source = SCENE.replace("albedo = [0.65, 0.05, 0.05]", "albedo = \"granite\"");
+        let err = parse_scene(&source, Path::new("")).err().unwrap();
+        assert!(err.to_string() == "line 11: unknown texture 'granite'");
+
+        let cyclic = r#"
+[textures.loop]
+type = "checker"
+scale = 1.0
+even = "loop"
+odd = "dark"
+"#;
+        let err = parse_scene(&format!("{SCENE}{textures}{cyclic}"), Path::new(""))
+            .err()
+            .unwrap();
+        assert!(err.to_string().ends_with("texture 'loop' refers to itself"));
+
+        let cyclic = r#"
+[textures.a]
+type = "checker"
+scale = 1.0
+even = "b"
+odd = "dark"
+
+[textures.b]
+type = "checker"
+scale = 1.0
+even = "dark"
+odd = "a"
+"#;
+        let err = parse_scene(&format!("{SCENE}{textures}{cyclic}"), Path::new(""))
+            .err()
+            .unwrap();
+        assert!(err
+            .to_string()
+            .ends_with("textures refer to each other in a cycle: 'a' -> 'b' -> 'a'"));
+    }
+}
@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+/// Labels every hit on an object with the object's ID and the ID of the
+/// material it hit, for the ID passes.
+pub struct Tagged {
+    object: Arc<dyn Hittable>,
+    object_id: u32,
+    /// The IDs of the materials the object is made of.
+    materials: Vec<(Arc<dyn Material>, u32)>,
+}
+
+impl Tagged {
+    /// Tags hits on `object` with `object_id`, and with the ID paired with
+    /// their material in `materials`, or 0 for materials not listed.
+    pub fn new(
+        object: Arc<dyn Hittable>,
+        object_id: u32,
+        materials: Vec<(Arc<dyn Material>, u32)>,
+    ) -> Self {
+        Self {
+            object,
+            object_id,
+            materials,
+        }
+    }
+}
+
+impl Hittable for Tagged {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        if !self.object.hit(r, ray_t, rec) {
+            return false;
+        }
+
+        rec.object_id = self.object_id;
+        // Most objects have a single material; OBJ groups add a few more.
+        rec.material_id = self
+            .materials
+            .iter()
+            .find(|(mat, _)| Arc::ptr_eq(mat, &rec.mat))
+            .map_or(0, |&(_, id)| id);
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.object.bounding_box()
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        self.object.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: Point3, u: [f64; 2]) -> Vec3 {
+        self.object.random(origin, u)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colour::Colour;
+    use crate::material::Lambertian;
+    use crate::sphere::Sphere;
+
+    #[test]
+    fn test_tagged_hit() {
+        let red: Arc<dyn Material> = Arc::new(Lambertian::new(Colour::new(1.0, 0.0, 0.0)));
+        let blue: Arc<dyn Material> = Arc::new(Lambertian::new(Colour::new(0.0, 0.0, 1.0)));
+        let sphere = Arc::new(Sphere::new(Point3::new(0.0, 0.0, -2.0), 0.5, blue.clone()));
+        let tagged = Tagged::new(sphere, 3, vec![(red, 1), (blue, 2)]);
+
+        let mut rec = HitRecord::default();
+        let r = Ray::new(Point3::default(), Vec3::new(0.0, 0.0, -1.0));
+        assert!(tagged.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+        assert!(rec.object_id == 3 && rec.material_id == 2);
+
+        let miss = Ray::new(Point3::default(), Vec3::new(0.0, 1.0, 0.0));
+        let mut rec = HitRecord::default();
+        assert!(!tagged.hit(&miss, Interval::new(0.001, f64::INFINITY), &mut rec));
+        assert!(rec.object_id == 0 && rec.material_id == 0);
+    }
+}
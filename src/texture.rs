@@ -0,0 +1,280 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::colour::Colour;
+use crate::interval::Interval;
+use crate::tonemap;
+use crate::vec3::Point3;
+
+pub trait Texture: Sync + Send {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Colour;
+}
+
+pub struct SolidColour {
+    pub albedo: Colour,
+}
+
+impl SolidColour {
+    pub fn new(albedo: Colour) -> Self {
+        Self { albedo }
+    }
+}
+
+impl Texture for SolidColour {
+    fn value(&self, _u: f64, _v: f64, _p: &Point3) -> Colour {
+        self.albedo
+    }
+}
+
+/// A solid 3D checker pattern of unit cells `scale` wide.
+pub struct CheckerTexture {
+    inv_scale: f64,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+}
+
+impl CheckerTexture {
+    pub fn new(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+        Self {
+            inv_scale: 1.0 / scale,
+            even,
+            odd,
+        }
+    }
+
+    pub fn from_colours(scale: f64, even: Colour, odd: Colour) -> Self {
+        CheckerTexture::new(
+            scale,
+            Arc::new(SolidColour::new(even)),
+            Arc::new(SolidColour::new(odd)),
+        )
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Colour {
+        let x = (self.inv_scale * p.x()).floor() as i64;
+        let y = (self.inv_scale * p.y()).floor() as i64;
+        let z = (self.inv_scale * p.z()).floor() as i64;
+
+        match (x + y + z) % 2 == 0 {
+            true => self.even.value(u, v, p),
+            false => self.odd.value(u, v, p),
+        }
+    }
+}
+
+/// A texture mapped from an image, stored as linear colours.
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Colour>,
+}
+
+impl ImageTexture {
+    /// Loads a PNG or PPM (P3 or P6) image, chosen by its extension.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+        let reader = BufReader::new(File::open(path)?);
+        match extension.as_deref() {
+            Some("png") => ImageTexture::read_png(reader),
+            Some("ppm") => ImageTexture::read_ppm(reader),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unsupported texture format: {}", path.display()),
+            )),
+        }
+    }
+
+    /// Builds a texture from 8-bit sRGB-encoded RGB triples in scanline order.
+    pub fn from_rgb8(width: usize, height: usize, rgb: &[u8]) -> io::Result<Self> {
+        if width == 0 || height == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "image has no pixels",
+            ));
+        }
+        if width.checked_mul(height).and_then(|n| n.checked_mul(3)) != Some(rgb.len()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "image data does not match its size",
+            ));
+        }
+
+        let pixels = rgb
+            .chunks_exact(3)
+            .map(|c| {
+                Colour::new(
+                    byte_to_linear(c[0]),
+                    byte_to_linear(c[1]),
+                    byte_to_linear(c[2]),
+                )
+            })
+            .collect();
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn read_png(reader: impl BufRead + io::Seek) -> io::Result<Self> {
+        let mut decoder = png::Decoder::new(reader);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(io::Error::other)?;
+        let buffer_size = reader
+            .output_buffer_size()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "PNG image is too large"))?;
+        let mut buffer = vec![0; buffer_size];
+        let info = reader.next_frame(&mut buffer).map_err(io::Error::other)?;
+        let data = &buffer[..info.buffer_size()];
+
+        let rgb: Vec<u8> = match info.color_type {
+            png::ColorType::Rgb => data.to_vec(),
+            png::ColorType::Rgba => data
+                .chunks_exact(4)
+                .flat_map(|c| [c[0], c[1], c[2]])
+                .collect(),
+            png::ColorType::Grayscale => data.iter().flat_map(|&g| [g, g, g]).collect(),
+            png::ColorType::GrayscaleAlpha => data
+                .chunks_exact(2)
+                .flat_map(|c| [c[0], c[0], c[0]])
+                .collect(),
+            png::ColorType::Indexed => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "unexpanded palette in PNG image",
+                ))
+            }
+        };
+        ImageTexture::from_rgb8(info.width as usize, info.height as usize, &rgb)
+    }
+
+    pub fn read_ppm(mut reader: impl BufRead) -> io::Result<Self> {
+        let invalid =
+            |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+        let mut contents = Vec::new();
+        reader.read_to_end(&mut contents)?;
+
+        // The header is whitespace-separated tokens, with comments running to the end of a line.
+        let mut pos = 0;
+        let mut next_token = || -> Option<String> {
+            loop {
+                while pos < contents.len() && contents[pos].is_ascii_whitespace() {
+                    pos += 1;
+                }
+                if pos < contents.len() && contents[pos] == b'#' {
+                    while pos < contents.len() && contents[pos] != b'\n' {
+                        pos += 1;
+                    }
+                    continue;
+                }
+                break;
+            }
+            let start = pos;
+            while pos < contents.len() && !contents[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            (pos > start).then(|| String::from_utf8_lossy(&contents[start..pos]).into_owned())
+        };
+
+        let magic = next_token().ok_or_else(|| invalid("empty PPM file"))?;
+        let mut number = || -> io::Result<usize> {
+            next_token()
+                .and_then(|token| token.parse().ok())
+                .ok_or_else(|| invalid("malformed PPM header"))
+        };
+        let width = number()?;
+        let height = number()?;
+        let max_value = number()?;
+        if max_value == 0 || max_value > 255 {
+            return Err(invalid("only 8-bit PPM images are supported"));
+        }
+        let scale = |value: usize| (value * 255 / max_value) as u8;
+        let size = width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(3))
+            .filter(|&size| size > 0)
+            .ok_or_else(|| invalid("PPM image has no pixels or is too large"))?;
+
+        let rgb: Vec<u8> = match magic.as_str() {
+            "P3" => (0..size)
+                .map(|_| number().map(scale))
+                .collect::<io::Result<_>>()?,
+            "P6" => {
+                // A single whitespace byte separates the header from the raster.
+                let start = pos + 1;
+                let raster = contents
+                    .get(start..start + size)
+                    .ok_or_else(|| invalid("truncated PPM raster"))?;
+                raster.iter().map(|&value| scale(value as usize)).collect()
+            }
+            _ => return Err(invalid("not a P3 or P6 PPM file")),
+        };
+        ImageTexture::from_rgb8(width, height, &rgb)
+    }
+}
+
+fn byte_to_linear(value: u8) -> f64 {
+    tonemap::srgb_eotf(value as f64 / 255.0)
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> Colour {
+        let unit_interval = Interval::new(0.0, 1.0);
+        let u = unit_interval.clamp(u);
+        let v = 1.0 - unit_interval.clamp(v);
+
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height - 1);
+        self.pixels[j * self.width + i]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checker() {
+        let checker = CheckerTexture::from_colours(
+            1.0,
+            Colour::new(1.0, 1.0, 1.0),
+            Colour::new(0.0, 0.0, 0.0),
+        );
+        let white = Colour::new(1.0, 1.0, 1.0);
+        assert!(checker.value(0.0, 0.0, &Point3::new(0.5, 0.5, 0.5)) == white);
+        assert!(checker.value(0.0, 0.0, &Point3::new(1.5, 0.5, 0.5)) != white);
+        assert!(checker.value(0.0, 0.0, &Point3::new(-0.5, 0.5, 0.5)) != white);
+        assert!(checker.value(0.0, 0.0, &Point3::new(-0.5, -0.5, 0.5)) == white);
+    }
+
+    #[test]
+    fn test_read_ppm() {
+        let p3 = "P3\n# two pixels\n2 1\n255\n255 0 0  0 0 255\n";
+        let p6 = b"P6\n2 1\n255\n\xff\x00\x00\x00\x00\xff";
+        for texture in [
+            ImageTexture::read_ppm(p3.as_bytes()).unwrap(),
+            ImageTexture::read_ppm(&p6[..]).unwrap(),
+        ] {
+            let p = Point3::default();
+            assert!(texture.value(0.25, 0.5, &p) == Colour::new(1.0, 0.0, 0.0));
+            assert!(texture.value(0.75, 0.5, &p) == Colour::new(0.0, 0.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn test_empty_images_are_rejected() {
+        assert!(ImageTexture::read_ppm(&b"P6\n0 1\n255\n"[..]).is_err());
+        assert!(ImageTexture::read_ppm("P3\n1 0\n255\n".as_bytes()).is_err());
+        assert!(ImageTexture::from_rgb8(0, 1, &[]).is_err());
+        assert!(ImageTexture::from_rgb8(2, 1, &[0, 0, 0]).is_err());
+        assert!(ImageTexture::from_rgb8(1, 1, &[0, 0, 0]).is_ok());
+    }
+}
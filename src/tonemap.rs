@@ -0,0 +1,320 @@
+use std::str::FromStr;
+
+use crate::colour::Colour;
+
+/// How a [`ToneMap`] compresses scene radiance into the displayable range.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ToneOperator {
+    /// Clip each channel at 1, as the renderer always used to.
+    #[default]
+    Clamp,
+    /// `L / (1 + L)` on luminance, which never quite reaches white.
+    Reinhard,
+    /// Reinhard, stretched so luminance `white` maps to white.
+    ExtendedReinhard { white: f64 },
+    /// Stephen Hill's fit of the ACES reference and sRGB output transforms.
+    Aces,
+    /// Troy Sobotka's AgX, in the polynomial approximation of its default look,
+    /// which desaturates highlights towards white rather than skewing their hue.
+    Agx,
+}
+
+impl FromStr for ToneOperator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "clamp" => Ok(ToneOperator::Clamp),
+            "reinhard" => Ok(ToneOperator::Reinhard),
+            "extended-reinhard" | "extended_reinhard" => Ok(ToneOperator::ExtendedReinhard {
+                white: DEFAULT_WHITE_POINT,
+            }),
+            "aces" => Ok(ToneOperator::Aces),
+            "agx" => Ok(ToneOperator::Agx),
+            _ => Err(format!(
+                "unknown tone operator '{s}'; expected clamp, reinhard, extended-reinhard, aces or agx"
+            )),
+        }
+    }
+}
+
+/// The luminance extended Reinhard maps to white unless told otherwise.
+pub const DEFAULT_WHITE_POINT: f64 = 4.0;
+
+/// The transform from linear rendered radiance to 8-bit sRGB: exposure, white
+/// balance, a tone operator and the sRGB transfer function, in that order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToneMap {
+    /// Exposure adjustment in stops; each one doubles the brightness.
+    pub exposure: f64,
+    pub operator: ToneOperator,
+    white_balance: Option<(f64, Mat3)>,
+}
+
+impl Default for ToneMap {
+    fn default() -> Self {
+        Self {
+            exposure: 0.0,
+            operator: ToneOperator::default(),
+            white_balance: None,
+        }
+    }
+}
+
+impl ToneMap {
+    pub fn new(exposure: f64, operator: ToneOperator) -> Self {
+        Self {
+            exposure,
+            operator,
+            white_balance: None,
+        }
+    }
+
+    /// The colour temperature in kelvin rendered as neutral, if any.
+    pub fn white_balance(&self) -> Option<f64> {
+        self.white_balance.map(|(kelvin, _)| kelvin)
+    }
+
+    /// Renders light of colour temperature `kelvin` as neutral white, by a
+    /// Bradford chromatic adaptation from it to D65; `None` leaves colours be.
+    pub fn set_white_balance(&mut self, kelvin: Option<f64>) {
+        self.white_balance = kelvin.map(|kelvin| (kelvin, white_balance_matrix(kelvin)));
+    }
+
+    /// `c` mapped to linear display values in `[0, 1]`.
+    pub fn apply(&self, c: Colour) -> Colour {
+        let mut c = 2.0_f64.powf(self.exposure) * c;
+        if let Some((_, matrix)) = &self.white_balance {
+            c = mul(matrix, c);
+        }
+        let c = match self.operator {
+            ToneOperator::Clamp => c,
+            ToneOperator::Reinhard => scale_luminance(c, |l| l / (1.0 + l)),
+            ToneOperator::ExtendedReinhard { white } => {
+                scale_luminance(c, |l| l * (1.0 + l / (white * white)) / (1.0 + l))
+            }
+            ToneOperator::Aces => aces(c),
+            ToneOperator::Agx => agx(c),
+        };
+        Colour::new(
+            c.x().clamp(0.0, 1.0),
+            c.y().clamp(0.0, 1.0),
+            c.z().clamp(0.0, 1.0),
+        )
+    }
+
+    /// `c` tone mapped and encoded as 8-bit sRGB.
+    pub fn to_rgb8(&self, c: Colour) -> [u8; 3] {
+        let c = self.apply(c);
+        [c.x(), c.y(), c.z()].map(|v| (255.0 * srgb_oetf(v) + 0.5) as u8)
+    }
+}
+
+/// The sRGB transfer function, from linear light to encoded values.
+pub fn srgb_oetf(linear: f64) -> f64 {
+    if linear <= 0.0031308 {
+        12.92 * linear
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// The inverse of [`srgb_oetf`], for decoding sRGB images.
+pub fn srgb_eotf(encoded: f64) -> f64 {
+    if encoded <= 0.04045 {
+        encoded / 12.92
+    } else {
+        ((encoded + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+type Mat3 = [[f64; 3]; 3];
+
+fn mul(m: &Mat3, c: Colour) -> Colour {
+    Colour::new(
+        m[0][0] * c.x() + m[0][1] * c.y() + m[0][2] * c.z(),
+        m[1][0] * c.x() + m[1][1] * c.y() + m[1][2] * c.z(),
+        m[2][0] * c.x() + m[2][1] * c.y() + m[2][2] * c.z(),
+    )
+}
+
+fn mat_mul(a: &Mat3, b: &Mat3) -> Mat3 {
+    let mut m = [[0.0; 3]; 3];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    m
+}
+
+fn luminance(c: Colour) -> f64 {
+    0.2126 * c.x() + 0.7152 * c.y() + 0.0722 * c.z()
+}
+
+/// Scales `c` so its luminance becomes `f` of what it was, keeping its hue.
+fn scale_luminance(c: Colour, f: impl Fn(f64) -> f64) -> Colour {
+    let l = luminance(c);
+    if l <= 0.0 {
+        return Colour::default();
+    }
+    f(l) / l * c
+}
+
+const SRGB_TO_XYZ: Mat3 = [
+    [0.4124564, 0.3575761, 0.1804375],
+    [0.2126729, 0.7151522, 0.0721750],
+    [0.0193339, 0.1191920, 0.9503041],
+];
+
+const XYZ_TO_SRGB: Mat3 = [
+    [3.2404542, -1.5371385, -0.4985314],
+    [-0.9692660, 1.8760108, 0.0415560],
+    [0.0556434, -0.2040259, 1.0572252],
+];
+
+const BRADFORD: Mat3 = [
+    [0.8951, 0.2664, -0.1614],
+    [-0.7502, 1.7135, 0.0367],
+    [0.0389, -0.0685, 1.0296],
+];
+
+const BRADFORD_INVERSE: Mat3 = [
+    [0.9869929, -0.1470543, 0.1599627],
+    [0.4323053, 0.5183603, 0.0492912],
+    [-0.0085287, 0.0400428, 0.9684867],
+];
+
+/// The chromaticity of a black body at `kelvin`, by Kim et al.'s cubic fit
+/// to the Planckian locus between 1667 K and 25000 K.
+fn planckian_xy(kelvin: f64) -> (f64, f64) {
+    let t = kelvin.clamp(1667.0, 25000.0);
+    let (t2, t3) = (t * t, t * t * t);
+    let x = if t <= 4000.0 {
+        -0.2661239e9 / t3 - 0.2343589e6 / t2 + 0.8776956e3 / t + 0.179910
+    } else {
+        -3.0258469e9 / t3 + 2.1070379e6 / t2 + 0.2226347e3 / t + 0.240390
+    };
+    let (x2, x3) = (x * x, x * x * x);
+    let y = if t <= 2222.0 {
+        -1.1063814 * x3 - 1.34811020 * x2 + 2.18555832 * x - 0.20219683
+    } else if t <= 4000.0 {
+        -0.9549476 * x3 - 1.37418593 * x2 + 2.09137015 * x - 0.16748867
+    } else {
+        3.0817580 * x3 - 5.87338670 * x2 + 3.75112997 * x - 0.37001483
+    };
+    (x, y)
+}
+
+/// The XYZ colour of unit luminance with chromaticity `(x, y)`.
+fn xy_to_xyz((x, y): (f64, f64)) -> Colour {
+    Colour::new(x / y, 1.0, (1.0 - x - y) / y)
+}
+
+fn white_balance_matrix(kelvin: f64) -> Mat3 {
+    let source = mul(&BRADFORD, xy_to_xyz(planckian_xy(kelvin)));
+    let target = mul(&BRADFORD, xy_to_xyz((0.3127, 0.3290)));
+    let scale = [
+        [target.x() / source.x(), 0.0, 0.0],
+        [0.0, target.y() / source.y(), 0.0],
+        [0.0, 0.0, target.z() / source.z()],
+    ];
+    let adapt = mat_mul(&BRADFORD_INVERSE, &mat_mul(&scale, &BRADFORD));
+    mat_mul(&XYZ_TO_SRGB, &mat_mul(&adapt, &SRGB_TO_XYZ))
+}
+
+fn aces(c: Colour) -> Colour {
+    const INPUT: Mat3 = [
+        [0.59719, 0.35458, 0.04823],
+        [0.07600, 0.90834, 0.01566],
+        [0.02840, 0.13383, 0.83777],
+    ];
+    const OUTPUT: Mat3 = [
+        [1.60475, -0.53108, -0.07367],
+        [-0.10208, 1.10813, -0.00605],
+        [-0.00327, -0.07276, 1.07602],
+    ];
+    let rrt_and_odt =
+        |v: f64| (v * (v + 0.0245786) - 0.000090537) / (v * (0.983729 * v + 0.4329510) + 0.238081);
+    let c = mul(&INPUT, c);
+    mul(
+        &OUTPUT,
+        Colour::new(rrt_and_odt(c.x()), rrt_and_odt(c.y()), rrt_and_odt(c.z())),
+    )
+}
+
+fn agx(c: Colour) -> Colour {
+    const INSET: Mat3 = [
+        [0.842479062253094, 0.0784335999999992, 0.0792237451477643],
+        [0.0423282422610123, 0.878468636469772, 0.0791661274605434],
+        [0.0423756549057051, 0.0784336, 0.879142973793104],
+    ];
+    const OUTSET: Mat3 = [
+        [1.19687900512017, -0.0980208811401368, -0.0990297440797205],
+        [-0.0528968517574562, 1.15190312990417, -0.0989611768448433],
+        [-0.0529716355144438, -0.0980434501171241, 1.15107367264116],
+    ];
+    const MIN_EV: f64 = -12.47393;
+    const MAX_EV: f64 = 4.026069;
+
+    let contrast = |v: f64| {
+        let x = (v.max(1e-10).log2().clamp(MIN_EV, MAX_EV) - MIN_EV) / (MAX_EV - MIN_EV);
+        let (x2, x4) = (x * x, x * x * x * x);
+        15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x
+            - 0.00232
+    };
+    let c = mul(&INSET, c);
+    let c = mul(
+        &OUTSET,
+        Colour::new(contrast(c.x()), contrast(c.y()), contrast(c.z())),
+    );
+    // The look is graded for a 2.2 power display; undo that to get linear light.
+    Colour::new(
+        c.x().max(0.0).powf(2.2),
+        c.y().max(0.0).powf(2.2),
+        c.z().max(0.0).powf(2.2),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_srgb_round_trip() {
+        for v in [0.0, 0.002, 0.04, 0.18, 0.5, 1.0] {
+            assert!((srgb_eotf(srgb_oetf(v)) - v).abs() < 1e-12);
+        }
+        assert!(ToneMap::default().to_rgb8(Colour::new(0.18, 1.0, 7.0)) == [118, 255, 255]);
+    }
+
+    #[test]
+    fn test_operators_compress_highlights() {
+        for operator in [
+            ToneOperator::Reinhard,
+            ToneOperator::ExtendedReinhard { white: 4.0 },
+            ToneOperator::Aces,
+            ToneOperator::Agx,
+        ] {
+            let tonemap = ToneMap::new(0.0, operator);
+            let grey = |v| tonemap.apply(Colour::new(v, v, v)).x();
+            assert!(grey(0.0) < 0.01);
+            assert!(grey(0.18) < grey(1.0) && grey(1.0) < grey(8.0));
+            assert!(grey(8.0) <= 1.0 && grey(64.0) > 0.9);
+        }
+        let white = ToneMap::new(0.0, ToneOperator::ExtendedReinhard { white: 4.0 });
+        assert!((white.apply(Colour::new(4.0, 4.0, 4.0)).y() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_exposure_and_white_balance() {
+        let brighter = ToneMap::new(1.0, ToneOperator::Clamp);
+        assert!(brighter.apply(Colour::new(0.25, 0.1, 0.0)) == Colour::new(0.5, 0.2, 0.0));
+
+        let mut tonemap = ToneMap::default();
+        tonemap.set_white_balance(Some(3000.0));
+        let tungsten = mul(&XYZ_TO_SRGB, 0.5 * xy_to_xyz(planckian_xy(3000.0)));
+        assert!((tonemap.apply(tungsten) - Colour::new(0.5, 0.5, 0.5)).length() < 1e-3);
+        assert!(tonemap.white_balance() == Some(3000.0));
+    }
+}
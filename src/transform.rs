@@ -0,0 +1,178 @@
+use std::ops::Mul;
+
+use crate::rtweekend;
+use crate::vec3::{Point3, Vec3};
+
+/// A 4x4 affine transform, stored row-major and applied to column vectors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat4 {
+    m: [[f64; 4]; 4],
+}
+
+impl Mat4 {
+    pub const IDENTITY: Mat4 = Mat4 {
+        m: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ],
+    };
+
+    pub fn new(m: [[f64; 4]; 4]) -> Self {
+        Self { m }
+    }
+
+    pub fn translation(offset: Vec3) -> Self {
+        let mut t = Mat4::IDENTITY;
+        for row in 0..3 {
+            t.m[row][3] = offset[row];
+        }
+        t
+    }
+
+    pub fn scaling(factors: Vec3) -> Self {
+        let mut s = Mat4::IDENTITY;
+        for axis in 0..3 {
+            s.m[axis][axis] = factors[axis];
+        }
+        s
+    }
+
+    /// A rotation by `degrees` anticlockwise about `axis`, looking down the axis
+    /// towards the origin.
+    pub fn rotation(axis: Vec3, degrees: f64) -> Self {
+        let a = axis.unit_vector();
+        let (sin, cos) = rtweekend::degrees_to_radians(degrees).sin_cos();
+        let k = 1.0 - cos;
+        let (x, y, z) = (a.x(), a.y(), a.z());
+        Mat4::new([
+            [
+                cos + x * x * k,
+                x * y * k - z * sin,
+                x * z * k + y * sin,
+                0.0,
+            ],
+            [
+                y * x * k + z * sin,
+                cos + y * y * k,
+                y * z * k - x * sin,
+                0.0,
+            ],
+            [
+                z * x * k - y * sin,
+                z * y * k + x * sin,
+                cos + z * z * k,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn transform_point(&self, p: Point3) -> Point3 {
+        self.transform_vector(p) + Vec3::new(self.m[0][3], self.m[1][3], self.m[2][3])
+    }
+
+    /// Applies the linear part of the transform, ignoring the translation.
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let row = |r: [f64; 4]| r[0] * v.x() + r[1] * v.y() + r[2] * v.z();
+        Vec3::new(row(self.m[0]), row(self.m[1]), row(self.m[2]))
+    }
+
+    pub fn transpose(&self) -> Mat4 {
+        let mut t = Mat4::IDENTITY;
+        for (row, values) in self.m.iter().enumerate() {
+            for (col, value) in values.iter().enumerate() {
+                t.m[col][row] = *value;
+            }
+        }
+        t
+    }
+
+    /// The factor by which the transform scales volumes.
+    pub fn determinant(&self) -> f64 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    /// The inverse of an affine transform, or `None` if it collapses space
+    /// onto a plane, line or point.
+    pub fn inverse(&self) -> Option<Mat4> {
+        let m = &self.m;
+        let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| {
+            m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+        };
+        let c00 = cofactor(1, 2, 1, 2);
+        let c01 = -cofactor(1, 2, 0, 2);
+        let c02 = cofactor(1, 2, 0, 1);
+        let det = self.determinant();
+        if det.abs() < 1e-12 {
+            return None;
+        }
+
+        // The inverse of the linear part is its adjugate over the determinant.
+        let mut inverse = Mat4::new([
+            [c00, -cofactor(0, 2, 1, 2), cofactor(0, 1, 1, 2), 0.0],
+            [c01, cofactor(0, 2, 0, 2), -cofactor(0, 1, 0, 2), 0.0],
+            [c02, -cofactor(0, 2, 0, 1), cofactor(0, 1, 0, 1), 0.0],
+            [0.0, 0.0, 0.0, det],
+        ]);
+        for value in inverse.m.iter_mut().flatten() {
+            *value /= det;
+        }
+        let offset = inverse.transform_vector(Vec3::new(m[0][3], m[1][3], m[2][3]));
+        for row in 0..3 {
+            inverse.m[row][3] = -offset[row];
+        }
+        Some(inverse)
+    }
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Mat4::IDENTITY
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+
+    /// Composes two transforms; `a * b` applies `b` first.
+    fn mul(self, rhs: Mat4) -> Mat4 {
+        let mut product = [[0.0; 4]; 4];
+        for (row, values) in product.iter_mut().enumerate() {
+            for (col, value) in values.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[row][k] * rhs.m[k][col]).sum();
+            }
+        }
+        Mat4::new(product)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compose_and_invert() {
+        let transform = Mat4::translation(Vec3::new(1.0, 2.0, 3.0))
+            * Mat4::rotation(Vec3::new(0.0, 1.0, 0.0), 90.0)
+            * Mat4::scaling(Vec3::new(2.0, 2.0, 2.0));
+
+        let p = transform.transform_point(Point3::new(1.0, 0.0, 0.0));
+        assert!((p - Point3::new(1.0, 2.0, 1.0)).near_zero());
+
+        let inverse = transform.inverse().unwrap();
+        assert!((inverse.transform_point(p) - Point3::new(1.0, 0.0, 0.0)).near_zero());
+        let round_trip = transform * inverse;
+        for row in 0..4 {
+            for col in 0..4 {
+                assert!((round_trip.m[row][col] - Mat4::IDENTITY.m[row][col]).abs() < 1e-12);
+            }
+        }
+
+        assert!(Mat4::scaling(Vec3::new(1.0, 0.0, 1.0)).inverse().is_none());
+    }
+}
@@ -0,0 +1,153 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::{self, HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+pub struct Triangle {
+    q: Point3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+    normal: Vec3,
+    d: f64,
+    area: f64,
+}
+
+impl Triangle {
+    /// A triangle with vertices `a`, `b` and `c`; the front face is the one they wind anticlockwise around.
+    pub fn new(a: Point3, b: Point3, c: Point3, mat: Arc<dyn Material>) -> Self {
+        let u = b - a;
+        let v = c - a;
+        let n = u.cross(v);
+        let normal = n.unit_vector();
+
+        Self {
+            q: a,
+            u,
+            v,
+            w: n / n.dot(n),
+            mat,
+            bbox: Aabb::enclosing(&Aabb::from_points(a, b), &Aabb::from_points(a, c)),
+            normal,
+            d: normal.dot(a),
+            area: 0.5 * n.length(),
+        }
+    }
+
+    /// The barycentric weights of `b` and `c` for a point `p` in the triangle's plane.
+    pub fn barycentric(&self, p: Point3) -> (f64, f64) {
+        let planar_hitpt_vector = p - self.q;
+        let alpha = self.w.dot(planar_hitpt_vector.cross(self.v));
+        let beta = self.w.dot(self.u.cross(planar_hitpt_vector));
+        (alpha, beta)
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let denom = self.normal.dot(r.direction());
+        if denom.abs() < 1e-8 {
+            return false;
+        }
+
+        let t = (self.d - self.normal.dot(r.origin())) / denom;
+        if !ray_t.contains(t) {
+            return false;
+        }
+
+        let intersection = r.at(t);
+        let (alpha, beta) = self.barycentric(intersection);
+        if alpha < 0.0 || beta < 0.0 || alpha + beta > 1.0 {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = intersection;
+        rec.u = alpha;
+        rec.v = beta;
+        rec.mat = self.mat.clone();
+        rec.set_face_normal(r, &self.normal);
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        hittable::area_pdf_value(self, self.area, origin, direction)
+    }
+
+    fn random(&self, origin: Point3, u: [f64; 2]) -> Vec3 {
+        // Folding the unit square along its diagonal keeps the samples uniform over the triangle.
+        let (mut alpha, mut beta) = (u[0], u[1]);
+        if alpha + beta > 1.0 {
+            (alpha, beta) = (1.0 - alpha, 1.0 - beta);
+        }
+        self.q + (alpha * self.u) + (beta * self.v) - origin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colour::Colour;
+    use crate::material::Lambertian;
+
+    fn triangle() -> Triangle {
+        Triangle::new(
+            Point3::new(0.0, 0.0, -2.0),
+            Point3::new(2.0, 0.0, -2.0),
+            Point3::new(0.0, 2.0, -2.0),
+            Arc::new(Lambertian::new(Colour::default())),
+        )
+    }
+
+    #[test]
+    fn test_hit() {
+        let triangle = triangle();
+        let inside = Ray::new(Point3::new(0.5, 1.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let outside = Ray::new(Point3::new(1.5, 1.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let behind = Ray::new(Point3::new(0.5, 1.0, -4.0), Vec3::new(0.0, 0.0, 1.0));
+
+        let mut rec = HitRecord::default();
+        assert!(triangle.hit(&inside, Interval::new(0.001, f64::INFINITY), &mut rec));
+        assert!(rec.t == 2.0);
+        assert!(rec.front_face);
+        assert!(rec.normal == Vec3::new(0.0, 0.0, 1.0));
+        // u and v are the barycentric weights of the second and third vertices.
+        assert!(rec.u == 0.25 && rec.v == 0.5);
+
+        assert!(triangle.hit(&behind, Interval::new(0.001, f64::INFINITY), &mut rec));
+        assert!(!rec.front_face);
+        assert!(rec.normal == Vec3::new(0.0, 0.0, -1.0));
+
+        assert!(!triangle.hit(&outside, Interval::new(0.001, f64::INFINITY), &mut rec));
+    }
+
+    #[test]
+    fn test_area_sampling() {
+        let triangle = triangle();
+        let origin = Point3::new(0.5, 0.5, 0.0);
+        for u in [[0.1, 0.2], [0.9, 0.7], [0.5, 0.5], [0.3, 0.95]] {
+            let direction = triangle.random(origin, u);
+            let mut rec = HitRecord::default();
+            let r = Ray::new(origin, direction);
+            assert!(triangle.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+            assert!((rec.t - 1.0).abs() < 1e-9);
+
+            let cosine = direction.z().abs() / direction.length();
+            let expected = direction.length_squared() / (cosine * 2.0);
+            assert!((triangle.pdf_value(origin, direction) - expected).abs() < 1e-9);
+        }
+        let away = Vec3::new(0.0, 0.0, 1.0);
+        assert!(triangle.pdf_value(origin, away) == 0.0);
+    }
+}
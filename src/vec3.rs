@@ -1,7 +1,7 @@
 use std::fmt;
 use std::ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub};
 
-use crate::rtweekend::{random_float, random_float_range};
+use crate::rtweekend::{random_float, random_float_range, Rng};
 
 #[derive(Debug, Default, Copy, Clone, PartialEq)]
 pub struct Vec3 {
@@ -49,11 +49,11 @@ impl Vec3 {
         *self / self.length()
     }
 
-    pub fn random_in_unit_disk() -> Vec3 {
+    pub fn random_in_unit_disk(rng: &mut Rng) -> Vec3 {
         loop {
             let p = Vec3::new(
-                random_float_range(-1.0, 1.0),
-                random_float_range(-1.0, 1.0),
+                random_float_range(-1.0, 1.0, rng),
+                random_float_range(-1.0, 1.0, rng),
                 0.0,
             );
             if p.length_squared() < 1.0 {
@@ -62,33 +62,33 @@ impl Vec3 {
         }
     }
 
-    pub fn random() -> Vec3 {
-        Vec3::new(random_float(), random_float(), random_float())
+    pub fn random(rng: &mut Rng) -> Vec3 {
+        Vec3::new(random_float(rng), random_float(rng), random_float(rng))
     }
 
-    pub fn random_range(min: f64, max: f64) -> Vec3 {
+    pub fn random_range(min: f64, max: f64, rng: &mut Rng) -> Vec3 {
         Vec3::new(
-            random_float_range(min, max),
-            random_float_range(min, max),
-            random_float_range(min, max),
+            random_float_range(min, max, rng),
+            random_float_range(min, max, rng),
+            random_float_range(min, max, rng),
         )
     }
 
-    pub fn random_in_unit_sphere() -> Vec3 {
+    pub fn random_in_unit_sphere(rng: &mut Rng) -> Vec3 {
         loop {
-            let p = Vec3::random_range(-1.0, 1.0);
+            let p = Vec3::random_range(-1.0, 1.0, rng);
             if p.length_squared() < 1.0 {
                 return p;
             }
         }
     }
 
-    pub fn random_unit_vector() -> Vec3 {
-        Vec3::random_in_unit_sphere().unit_vector()
+    pub fn random_unit_vector(rng: &mut Rng) -> Vec3 {
+        Vec3::random_in_unit_sphere(rng).unit_vector()
     }
 
-    pub fn random_on_hemisphere(normal: Vec3) -> Vec3 {
-        let on_unit_sphere = Vec3::random_unit_vector();
+    pub fn random_on_hemisphere(normal: Vec3, rng: &mut Rng) -> Vec3 {
+        let on_unit_sphere = Vec3::random_unit_vector(rng);
         match on_unit_sphere.dot(normal) > 0.0 {
             true => on_unit_sphere,
             false => -on_unit_sphere,
@@ -208,6 +208,43 @@ impl Sub for Vec3 {
 
 pub type Point3 = Vec3;
 
+/// An orthonormal basis whose `w` axis points along a given direction.
+#[derive(Debug, Clone, Copy)]
+pub struct Onb {
+    axis: [Vec3; 3],
+}
+
+impl Onb {
+    pub fn new(n: Vec3) -> Self {
+        let w = n.unit_vector();
+        let a = if w.x().abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = w.cross(a).unit_vector();
+        let u = v.cross(w);
+        Self { axis: [u, v, w] }
+    }
+
+    pub fn u(&self) -> Vec3 {
+        self.axis[0]
+    }
+
+    pub fn v(&self) -> Vec3 {
+        self.axis[1]
+    }
+
+    pub fn w(&self) -> Vec3 {
+        self.axis[2]
+    }
+
+    /// Converts coordinates relative to this basis into world coordinates.
+    pub fn transform(&self, v: Vec3) -> Vec3 {
+        (v[0] * self.axis[0]) + (v[1] * self.axis[1]) + (v[2] * self.axis[2])
+    }
+}
+
 impl fmt::Display for Vec3 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{} {} {}", self.x(), self.y(), self.z())
@@ -287,4 +324,13 @@ mod tests {
     fn test_neg() {
         assert!(-Vec3::new(2.0, 4.0, 6.0) == Vec3::new(-2.0, -4.0, -6.0))
     }
+
+    #[test]
+    fn test_onb() {
+        let uvw = Onb::new(Vec3::new(0.0, 3.0, 4.0));
+        assert!((uvw.w() - Vec3::new(0.0, 0.6, 0.8)).near_zero());
+        assert!(uvw.u().dot(uvw.v()).abs() < 1e-12 && uvw.u().dot(uvw.w()).abs() < 1e-12);
+        assert!((uvw.u().cross(uvw.v()) - uvw.w()).near_zero());
+        assert!((uvw.transform(Vec3::new(0.0, 0.0, 2.0)) - Vec3::new(0.0, 1.2, 1.6)).near_zero());
+    }
 }